@@ -3,8 +3,7 @@
 // Please see the file LICENSE in the source
 // distribution of this software for license terms.
 
-// Sort an array of random numbers with quicksort
-// and print the median.
+// Print the median of an array of random numbers.
 
 extern crate quicksort;
 extern crate rand;
@@ -12,15 +11,17 @@ use rand::Rng;
 
 fn main() {
     let usage = "usage: median <count>";
-    let n = std::env::args().nth(1).expect(usage);
-    let n = n.parse().expect(usage);
-    if n <= 0 {
-        panic!(usage)
-    }
+    let n: usize = std::env::args()
+        .nth(1)
+        .expect(usage)
+        .parse()
+        .expect(usage);
     let mut a = Vec::with_capacity(n);
     for _ in 0..n {
         a.push(rand::thread_rng().gen_range(1, 2 * n))
     }
-    quicksort::quicksort(&mut a);
-    println!("{}", a[n / 2])
+    match quicksort::median(&a) {
+        Some(m) => println!("{}", m),
+        None => panic!("{}", usage),
+    }
 }
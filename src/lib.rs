@@ -7,16 +7,31 @@
 
 //! Quicksort
 
+// The core algorithms — the partitions, insertion sort
+// and a recursive `quicksort` — need only `core`; the
+// rest of the API allocates or otherwise wants `std` and
+// is gated behind the default `std` feature.
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
 #[cfg(test)]
 extern crate rand;
+#[cfg(feature = "rayon")]
+extern crate rayon;
+
+#[cfg(any(feature = "std", test))]
+use std::cmp::Ordering;
+#[cfg(not(any(feature = "std", test)))]
+use core::cmp::Ordering;
 
 /// Rearrange the elements of `slice`. Returns a "pivot"
-/// index into the slice.  On return, all elements at
-/// indices less than or equal to the pivot index will be
-/// less than or equal to the value at the pivot index, and
-/// all elements at indices greater than or equal to the
-/// pivot index will be greater than or equal to the value
-/// at the pivot index.
+/// index into the slice, or `None` for slices of fewer
+/// than two elements, which have nothing to partition.
+/// On `Some` return, all elements at indices less than or
+/// equal to the pivot index will be less than or equal to
+/// the value at the pivot index, and all elements at
+/// indices greater than or equal to the pivot index will
+/// be greater than or equal to the value at the pivot
+/// index.
 ///
 /// Partitioning is done using a custom variant of Hoare's method
 /// designed to put the pivot reasonably close to the middle.
@@ -25,7 +40,7 @@ extern crate rand;
 ///
 /// ```
 /// let mut a = [5,1,0,2,2,4,3,2];
-/// let pivot = quicksort::partition(&mut a);
+/// let pivot = quicksort::partition(&mut a).unwrap();
 /// for (i, v) in a.into_iter().enumerate() {
 ///     if i <= pivot {
 ///         assert!(*v <= a[pivot])
@@ -33,17 +48,82 @@ extern crate rand;
 ///         assert!(*v > a[pivot])
 ///     }
 /// }
+/// assert_eq!(quicksort::partition(&mut [1]), None);
 /// ```
-pub fn partition<T: Ord>(slice: &mut [T]) -> usize {
+pub fn partition<T: Ord>(slice: &mut [T]) -> Option<usize> {
+    if slice.len() < 2 {
+        return None
+    }
+    Some(partition_by(slice, |a, b| a.cmp(b)))
+}
+
+/// Comparator version of `partition()`: rearranges the
+/// elements of `slice` around a pivot as described there,
+/// but with every ordering decision made by `compare`
+/// rather than `Ord`. The comparator is only ever called
+/// with references to elements of the slice. The
+/// partitioning invariants (and, in test builds, their
+/// assertions) hold whenever the comparator is consistent
+/// — transitive and symmetric in the usual way. An
+/// inconsistent comparator gets a weaker but safe
+/// guarantee: the call still terminates and returns an
+/// in-range index, merely one whose partition property is
+/// meaningless. Unlike `partition()`, this panics on
+/// slices of fewer than two elements; internal callers
+/// are expected to have checked.
+pub fn partition_by<T, F: FnMut(&T, &T) -> Ordering>(
+    slice: &mut [T],
+    mut compare: F,
+) -> usize {
+    partition_by_checked(slice, &mut compare, true)
+}
+
+// The partition loop proper. Termination and bounds
+// safety do not depend on the comparator: every pass
+// closes the `low`/`high` gap by exactly two before
+// placement, and each placement arm re-opens it by at
+// most one, so the gap shrinks monotonically and all
+// indexing stays between the two. The invariant
+// assertions are the only comparator-trusting code, so
+// they run only in test builds and only when `check` is
+// set — the adversarial-comparator test clears it to get
+// release behavior.
+fn partition_by_checked<T, F: FnMut(&T, &T) -> Ordering>(
+    slice: &mut [T],
+    compare: &mut F,
+    check: bool,
+) -> usize {
+    #[cfg(not(test))]
+    let _ = check;
     // Set up the length.
     let nslice = slice.len();
     if nslice < 2 {
         panic!("partition of short slice")
     }
 
+    // Median-of-three pivot seeding: order the first,
+    // middle and last elements, then bring the median of
+    // the three to the front. Sorted, reverse-sorted and
+    // organ-pipe inputs otherwise seed the partition bands
+    // from extreme values and drive the splits badly out
+    // of balance.
+    if nslice >= 3 {
+        let mid = nslice / 2;
+        if compare(&slice[0], &slice[mid]) == Ordering::Greater {
+            slice.swap(0, mid)
+        }
+        if compare(&slice[mid], &slice[nslice-1]) == Ordering::Greater {
+            slice.swap(mid, nslice-1)
+        }
+        if compare(&slice[0], &slice[mid]) == Ordering::Greater {
+            slice.swap(0, mid)
+        }
+        slice.swap(0, mid)
+    }
+
     // Things are easier if we order the first considered
     // elements.
-    if slice[0] > slice[nslice-1] {
+    if compare(&slice[0], &slice[nslice-1]) == Ordering::Greater {
         slice.swap(0, nslice-1)
     }
 
@@ -56,13 +136,15 @@ pub fn partition<T: Ord>(slice: &mut [T]) -> usize {
     // Partition the rest of the values.
     loop {
         // Check invariants.
-        assert!(slice[low_max] <= slice[high_min]);
         #[cfg(test)] {
-            for i in 0..low+1 {
-                assert!(slice[i] <= slice[low_max])
-            }
-            for i in high..nslice {
-                assert!(slice[i] >= slice[high_min])
+            if check {
+                assert!(compare(&slice[low_max], &slice[high_min]) != Ordering::Greater);
+                for i in 0..low+1 {
+                    assert!(compare(&slice[i], &slice[low_max]) != Ordering::Greater)
+                }
+                for i in high..nslice {
+                    assert!(compare(&slice[i], &slice[high_min]) != Ordering::Less)
+                }
             }
         }
 
@@ -78,7 +160,8 @@ pub fn partition<T: Ord>(slice: &mut [T]) -> usize {
             // little cost.
             for i in 1..ngap {
                 for j in (i + 1)..ngap {
-                    if slice[low + i] > slice[low + j] {
+                    if compare(&slice[low + i], &slice[low + j])
+                        == Ordering::Greater {
                         slice.swap(low + i, low + j)
                     }
                 }
@@ -87,9 +170,11 @@ pub fn partition<T: Ord>(slice: &mut [T]) -> usize {
             // Put all the values that are safe to include
             // into the low partition into it.
             for _ in 1..ngap {
-                if slice[low + 1] <= slice[high_min] {
+                if compare(&slice[low + 1], &slice[high_min])
+                    != Ordering::Greater {
                     low += 1;
-                    if slice[low] > slice[low_max] {
+                    if compare(&slice[low], &slice[low_max])
+                        == Ordering::Greater {
                         low_max = low
                     }
                 } else {
@@ -107,13 +192,15 @@ pub fn partition<T: Ord>(slice: &mut [T]) -> usize {
             }
 
             // Check the invariants one last time.
-            assert!(slice[low_max] <= slice[high_min]);
             #[cfg(test)] {
-                for (i, v) in slice.iter().enumerate() {
-                    if i <= pivot {
-                        assert!(*v <= slice[pivot])
-                    } else {
-                        assert!(*v >= slice[pivot])
+                if check {
+                    assert!(compare(&slice[low_max], &slice[high_min]) != Ordering::Greater);
+                    for (i, v) in slice.iter().enumerate() {
+                        if i <= pivot {
+                            assert!(compare(v, &slice[pivot]) != Ordering::Greater)
+                        } else {
+                            assert!(compare(v, &slice[pivot]) != Ordering::Less)
+                        }
                     }
                 }
             }
@@ -144,23 +231,23 @@ pub fn partition<T: Ord>(slice: &mut [T]) -> usize {
         let nhigh = nslice - high;
 
         // Figure out where to place the next two values.
-        if slice[low] < slice[low_max] &&
-        slice[high] < slice[low_max] {
+        if compare(&slice[low], &slice[low_max]) == Ordering::Less &&
+        compare(&slice[high], &slice[low_max]) == Ordering::Less {
             // Case: We are forced to place both values low.
             place = P::LOW
-        } else if slice[low] > slice[high_min] &&
-        slice[high] > slice[high_min] {
+        } else if compare(&slice[low], &slice[high_min]) == Ordering::Greater &&
+        compare(&slice[high], &slice[high_min]) == Ordering::Greater {
             // Case: We are forced to place both values high.
             place = P::HIGH
         } else if nlow + 1 < nhigh &&
-        slice[low] <= slice[high_min] &&
-        slice[high] <= slice[high_min] {
+        compare(&slice[low], &slice[high_min]) != Ordering::Greater &&
+        compare(&slice[high], &slice[high_min]) != Ordering::Greater {
             // Case: We are out of balance high, and can
             // place both values low.
             place = P::LOW
         } else if nhigh + 1 < nlow &&
-        slice[low] >= slice[low_max] &&
-        slice[high] >= slice[low_max] {
+        compare(&slice[low], &slice[low_max]) != Ordering::Less &&
+        compare(&slice[high], &slice[low_max]) != Ordering::Less {
             // Case: We are out of balance low, and can
             // place both values high.
             place = P::HIGH
@@ -180,10 +267,10 @@ pub fn partition<T: Ord>(slice: &mut [T]) -> usize {
                 slice.swap(low + 1, high);
 
                 // Update low_max as needed.
-                if slice[low] > slice[low_max] {
+                if compare(&slice[low], &slice[low_max]) == Ordering::Greater {
                     low_max = low
                 }
-                if slice[low + 1] > slice[low_max] {
+                if compare(&slice[low + 1], &slice[low_max]) == Ordering::Greater {
                     low_max = low + 1
                 }
 
@@ -200,10 +287,10 @@ pub fn partition<T: Ord>(slice: &mut [T]) -> usize {
                 slice.swap(low, high - 1);
 
                 // Update high_min as needed.
-                if slice[high] < slice[high_min] {
+                if compare(&slice[high], &slice[high_min]) == Ordering::Less {
                     high_min = high
                 }
-                if slice[high - 1] < slice[high_min] {
+                if compare(&slice[high - 1], &slice[high_min]) == Ordering::Less {
                     high_min = high - 1
                 }
 
@@ -216,14 +303,14 @@ pub fn partition<T: Ord>(slice: &mut [T]) -> usize {
                 // Place one value in each partition.
 
                 // Need the low value first.
-                if slice[low] > slice[high] {
+                if compare(&slice[low], &slice[high]) == Ordering::Greater {
                     slice.swap(low, high)
                 }
                 // Update low_max and high_min as needed.
-                if slice[low] > slice[low_max] {
+                if compare(&slice[low], &slice[low_max]) == Ordering::Greater {
                     low_max = low
                 }
-                if slice[high] < slice[high_min] {
+                if compare(&slice[high], &slice[high_min]) == Ordering::Less {
                     high_min = high
                 }
             }
@@ -240,7 +327,7 @@ fn partition_random() {
     for _ in 0..n {
         a.push(rand::thread_rng().gen_range(-50, 50))
     }
-    let pivot = partition(&mut a);
+    let pivot = partition(&mut a).unwrap();
     let pivot_val = a[pivot];
     for (i, v) in a.into_iter().enumerate() {
         if i <= pivot {
@@ -251,8 +338,58 @@ fn partition_random() {
     }
 }
 
+// Subranges no longer than this are finished with
+// insertion sort instead of being partitioned further:
+// for tiny subslices the partition bookkeeping costs more
+// than the sort.
+const INSERTION_THRESHOLD: usize = 16;
+
+// Straight insertion sort, for subranges below
+// `INSERTION_THRESHOLD`.
+fn insertion_sort<T: Ord>(slice: &mut [T]) {
+    insertion_sort_by(slice, &mut |a: &T, b: &T| a.cmp(b))
+}
+
+// Comparator version of `insertion_sort()`, for the
+// cutover in `quicksort_by()`.
+fn insertion_sort_by<T, F: FnMut(&T, &T) -> Ordering>(
+    slice: &mut [T],
+    compare: &mut F,
+) {
+    for i in 1..slice.len() {
+        // Walk element i down to its place.
+        let mut j = i;
+        while j > 0 && compare(&slice[j - 1], &slice[j]) == Ordering::Greater {
+            slice.swap(j - 1, j);
+            j -= 1
+        }
+    }
+}
+
+// Element size, in bytes, above which the default
+// `quicksort` switches to the index-permutation strategy
+// to cut down on data movement.
+#[cfg(not(feature = "stable"))]
+#[cfg(feature = "std")]
+const INDIRECT_SIZE_THRESHOLD: usize = 64;
+
 /// Sorts the elements of the slice using Quicksort via
-/// `quicksort::partition()`.
+/// `quicksort::partition()`. This is the fast unstable
+/// default; enabling the `stable` feature swaps in a
+/// stable implementation at every call site instead.
+///
+/// Elements bigger than 64 bytes are automatically sorted
+/// through `quicksort_indirect()`, which shuffles cheap
+/// indices during partitioning and moves each big element
+/// at most once at the end; smaller elements are swapped
+/// directly in place.
+///
+/// Partitioning is three-way (`partition_three_way()`):
+/// each pass settles the whole band of elements equal to
+/// the pivot, so duplicate-heavy inputs — an all-equal
+/// slice being the extreme — finish in one pass per
+/// distinct value rather than recursing through the
+/// duplicates.
 ///
 /// # Examples
 ///
@@ -263,19 +400,325 @@ fn partition_random() {
 ///     assert_eq!(i, *v)
 /// }
 /// ```
+#[cfg(not(feature = "stable"))]
+#[cfg(feature = "std")]
 pub fn quicksort<T: Ord>(slice: &mut [T]) {
-    if slice.len() <= 1 {
-        return;  // Nothing to sort.
+    if slice.len() <= INSERTION_THRESHOLD {
+        // Too small to be worth partitioning.
+        insertion_sort(slice);
+        return
+    }
+
+    // Bulky elements: sort indices instead and permute
+    // once.
+    if std::mem::size_of::<T>() > INDIRECT_SIZE_THRESHOLD {
+        quicksort_indirect(slice);
+        return
+    }
+
+    // Pending [lo, hi) subranges, managed explicitly as
+    // in `quicksort_by()` to bound the stack.
+    let mut stack: Vec<(usize, usize)> = Vec::new();
+    let mut lo = 0;
+    let mut hi = slice.len();
+    loop {
+        while hi - lo > INSERTION_THRESHOLD {
+            // Split off the bands below and above the
+            // pivot value; the equal band is done.
+            let (lt_end, gt_start) = partition_three_way(&mut slice[lo .. hi]);
+            let (lt_end, gt_start) = (lo + lt_end, lo + gt_start);
+
+            // Save the smaller band for later and continue
+            // with the larger.
+            if lt_end - lo < hi - gt_start {
+                stack.push((lo, lt_end));
+                lo = gt_start
+            } else {
+                stack.push((gt_start, hi));
+                hi = lt_end
+            }
+        }
+        // Finish the remnant by insertion.
+        insertion_sort(&mut slice[lo .. hi]);
+        match stack.pop() {
+            Some((l, h)) => {
+                lo = l;
+                hi = h
+            }
+            None => break,  // Nothing left to sort.
+        }
+    }
+
+    debug_assert!(is_sorted(slice));
+}
+
+/// Sorts the elements of the slice using Quicksort. This
+/// is the `no_std` build: no allocation is available for
+/// the explicit work stack, so it recurses natively on
+/// the bands of `partition_three_way()` instead — fine for
+/// the small fixed buffers embedded targets sort, but
+/// recursion depth is unbounded on adversarial inputs.
+#[cfg(all(not(feature = "std"), not(feature = "stable")))]
+pub fn quicksort<T: Ord>(slice: &mut [T]) {
+    if slice.len() <= INSERTION_THRESHOLD {
+        // Too small to be worth partitioning.
+        insertion_sort(slice);
+        return
+    }
+
+    let (lt_end, gt_start) = partition_three_way(slice);
+    quicksort(&mut slice[.. lt_end]);
+    quicksort(&mut slice[gt_start ..]);
+    debug_assert!(is_sorted(slice));
+}
+
+/// Rearranges the slice around a pivot value into three
+/// bands — the Dutch national flag partition — and returns
+/// `(lt_end, gt_start)`: elements in `[0, lt_end)` compare
+/// less than the pivot, those in `[lt_end, gt_start)`
+/// equal it, and those in `[gt_start, len)` greater. The
+/// pivot is the middle element's value. Unlike
+/// `partition()`, every duplicate of the pivot lands in
+/// the middle band in this one pass, so on an all-equal
+/// slice the band spans everything and `(0, len)` comes
+/// back. Panics on slices of fewer than two elements,
+/// like `partition()`.
+///
+/// # Examples
+///
+/// ```
+/// let mut a = [2, 1, 3, 2, 2, 1, 3, 2];
+/// let (lt_end, gt_start) = quicksort::partition_three_way(&mut a);
+/// assert_eq!((lt_end, gt_start), (2, 6));
+/// assert!(a[lt_end .. gt_start].iter().all(|&v| v == 2));
+/// ```
+pub fn partition_three_way<T: Ord>(slice: &mut [T]) -> (usize, usize) {
+    let nslice = slice.len();
+    if nslice < 2 {
+        panic!("partition of short slice")
+    }
+
+    // Put the pivot value first, then sweep: [0, lt) is
+    // below the pivot, [lt, i) is the equal band (pivot
+    // included), [gt, nslice) is above, and [i, gt) is
+    // still unexamined.
+    slice.swap(0, nslice / 2);
+    let mut lt = 0;
+    let mut i = 1;
+    let mut gt = nslice;
+    while i < gt {
+        match slice[i].cmp(&slice[lt]) {
+            Ordering::Less => {
+                slice.swap(i, lt);
+                lt += 1;
+                i += 1
+            }
+            Ordering::Equal => i += 1,
+            Ordering::Greater => {
+                gt -= 1;
+                slice.swap(i, gt)
+            }
+        }
+    }
+    (lt, gt)
+}
+
+#[test]
+fn partition_three_way_bands() {
+    let mut a = [2, 2, 2, 1, 1, 3, 3, 2];
+    let (lt_end, gt_start) = partition_three_way(&mut a);
+    let pivot = a[lt_end];
+    for (i, &v) in a.iter().enumerate() {
+        if i < lt_end {
+            assert!(v < pivot)
+        } else if i < gt_start {
+            assert_eq!(v, pivot)
+        } else {
+            assert!(v > pivot)
+        }
     }
 
-    // Partition the slice into two parts, front and back.
-    let pivot_index = partition(slice);
+    // All equal: the middle band spans the whole slice.
+    let mut a = [7; 30];
+    assert_eq!(partition_three_way(&mut a), (0, 30));
+    let mut a = [7; 30];
+    quicksort(&mut a);
+    assert_eq!(a, [7; 30])
+}
+
+/// Comparator version of `quicksort()`: sorts the slice
+/// into the order induced by `compare`, which should be a
+/// total order on the elements. Passing `|a, b| a.cmp(b)`
+/// gives the usual ascending sort; reversing the arguments
+/// sorts descending.
+///
+/// # Examples
+///
+/// ```
+/// let mut a = [5, 1, 0, 4, 3, 2];
+/// quicksort::quicksort_by(&mut a, |a, b| b.cmp(a));
+/// assert_eq!(a, [5, 4, 3, 2, 1, 0]);
+/// ```
+#[cfg(feature = "std")]
+pub fn quicksort_by<T, F: FnMut(&T, &T) -> Ordering>(slice: &mut [T], mut compare: F) {
+    quicksort_by_ref(slice, &mut compare)
+}
+
+// Worker for `quicksort_by()`. Native recursion would be
+// proportional to the partition depth, which is O(n) in
+// the worst case and blows the thread stack on
+// large-enough inputs, so subranges are managed on an
+// explicit work stack instead. After each partition the
+// larger side is looped on directly and only the smaller
+// side is pushed; every stacked range is thus at most half
+// of its parent, bounding the stack to O(log n) entries.
+#[cfg(feature = "std")]
+fn quicksort_by_ref<T, F: FnMut(&T, &T) -> Ordering>(slice: &mut [T], compare: &mut F) {
+    // Pending [lo, hi) subranges still to be sorted.
+    let mut stack: Vec<(usize, usize)> = Vec::new();
+    let mut lo = 0;
+    let mut hi = slice.len();
+    loop {
+        while hi - lo > INSERTION_THRESHOLD {
+            // Partition the subrange into two parts, front
+            // and back.
+            let pivot_index =
+                lo + partition_by(&mut slice[lo .. hi], &mut *compare);
+
+            // Save the smaller side for later and continue
+            // with the larger.
+            if pivot_index - lo < hi - pivot_index {
+                stack.push((lo, pivot_index));
+                lo = pivot_index + 1
+            } else {
+                stack.push((pivot_index + 1, hi));
+                hi = pivot_index
+            }
+        }
+        // Finish the remnant by insertion.
+        insertion_sort_by(&mut slice[lo .. hi], compare);
+        match stack.pop() {
+            Some((l, h)) => {
+                lo = l;
+                hi = h
+            }
+            None => return,  // Nothing left to sort.
+        }
+    }
+}
+
+#[test]
+fn quicksort_deep_input_no_overflow() {
+    // Descending input drives the partition to its
+    // worst-case splits, one level per element: deep
+    // enough to overflow the thread stack if the sort
+    // recursed natively per partition level.
+    let mut a: Vec<u32> = (0..30_000).rev().collect();
+    quicksort(&mut a);
+    for (i, v) in a.into_iter().enumerate() {
+        assert_eq!(i as u32, v)
+    }
+}
+
+#[test]
+fn quicksort_by_descending() {
+    let mut a = [3i64, 1, 4, 1, 5, 9, 2, 6];
+    quicksort_by(&mut a, |a, b| b.cmp(a));
+    for w in a.windows(2) {
+        assert!(w[0] >= w[1])
+    }
+}
+
+/// Sorts the elements of the slice using Quicksort. With
+/// the `stable` feature enabled — as it is in this build —
+/// equal elements additionally keep their original
+/// relative order. Stability is bought with the
+/// index-tiebreak approach: a permutation of original
+/// indices is sorted by `(value, index)` and then applied,
+/// costing `O(n)` extra memory for the index vectors where
+/// the unstable version sorts fully in place.
+///
+/// # Examples
+///
+/// ```
+/// let mut a = [5,1,0,4,3,2];
+/// quicksort::quicksort(&mut a);
+/// for (i, v) in a.into_iter().enumerate() {
+///     assert_eq!(i, *v)
+/// }
+/// ```
+#[cfg(feature = "stable")]
+#[cfg(feature = "std")]
+pub fn quicksort<T: Ord>(slice: &mut [T]) {
+    let nslice = slice.len();
+
+    // Sort original indices by value, breaking ties by
+    // index: that tiebreak is exactly stability.
+    let mut order: Vec<usize> = (0..nslice).collect();
+    quicksort_by_compare(&mut order, &mut |a: &usize, b: &usize| {
+        slice[*a].cmp(&slice[*b]).then_with(|| a.cmp(b))
+    });
+
+    // Invert to per-element destinations and apply with
+    // cycle-following swaps.
+    let mut dest = vec![0; nslice];
+    for (k, &src) in order.iter().enumerate() {
+        dest[src] = k
+    }
+    for i in 0..nslice {
+        while dest[i] != i {
+            let j = dest[i];
+            slice.swap(i, j);
+            dest.swap(i, j)
+        }
+    }
+
+    debug_assert!(is_sorted(slice));
+}
 
-    // Recursively sort the front half of `slice`.
-    quicksort(&mut slice[.. pivot_index]);
+#[cfg(feature = "stable")]
+#[test]
+fn quicksort_stable_feature_preserves_order() {
+    // Key-only ordering with a marker recording input
+    // order; the marker is invisible to `Ord`, so only a
+    // stable sort keeps markers in input order within each
+    // key.
+    struct Key(i32, char);
+
+    impl PartialEq for Key {
+        fn eq(&self, other: &Key) -> bool {
+            self.0 == other.0
+        }
+    }
+
+    impl Eq for Key {}
+
+    impl PartialOrd for Key {
+        fn partial_cmp(&self, other: &Key) -> Option<Ordering> {
+            Some(self.cmp(other))
+        }
+    }
 
-    // And the back half.
-    quicksort(&mut slice[pivot_index + 1 ..]);
+    impl Ord for Key {
+        fn cmp(&self, other: &Key) -> Ordering {
+            self.0.cmp(&other.0)
+        }
+    }
+
+    let mut keys = vec![
+        Key(2, 'a'), Key(1, 'b'), Key(2, 'c'), Key(1, 'd'), Key(2, 'e'),
+    ];
+    quicksort(&mut keys);
+    let markers: Vec<char> = keys.iter().map(|k| k.1).collect();
+    assert_eq!(markers, ['b', 'd', 'a', 'c', 'e'])
+}
+
+#[cfg(not(feature = "stable"))]
+#[test]
+fn quicksort_unstable_feature_sorts() {
+    let mut a = [5, 1, 0, 4, 3, 2, 2, 1];
+    quicksort(&mut a);
+    assert_eq!(a, [0, 1, 1, 2, 2, 3, 4, 5])
 }
 
 #[test]
@@ -301,3 +744,6346 @@ fn quicksort_random() {
         }
     }
 }
+
+// Partition `slice` around the element currently at index
+// `choice` using a simple Lomuto scan. Returns the final
+// index of that element. Unlike `partition()`, the caller
+// gets to say exactly which element is the pivot, which is
+// what the deterministic-pivot entry points need.
+fn partition_around<T: Ord>(slice: &mut [T], choice: usize) -> usize {
+    let nslice = slice.len();
+    assert!(choice < nslice);
+
+    // Park the pivot at the end, out of the way of the scan.
+    slice.swap(choice, nslice - 1);
+
+    // Sweep everything no larger than the pivot to the front.
+    let mut store = 0;
+    for i in 0..nslice - 1 {
+        if slice[i] <= slice[nslice - 1] {
+            slice.swap(i, store);
+            store += 1
+        }
+    }
+
+    // Drop the pivot into its final position.
+    slice.swap(store, nslice - 1);
+    store
+}
+
+/// Sorts the elements of the slice like `quicksort()`, but
+/// draws pivot choices from `pivots` so that tests can
+/// force a specific recursion tree. Each index consumed is
+/// interpreted relative to the current subrange being
+/// partitioned: `0` names the first element of that
+/// subrange, not of the whole slice. Out-of-range indices
+/// are clamped to the subrange. When the iterator runs dry,
+/// the remaining recursion falls back to the default
+/// `partition()`.
+///
+/// # Examples
+///
+/// ```
+/// let mut a = [3, 1, 4, 1, 5, 9, 2, 6];
+/// let mut pivots = std::iter::repeat(0);
+/// quicksort::quicksort_with_pivots(&mut a, &mut pivots);
+/// assert_eq!(a, [1, 1, 2, 3, 4, 5, 6, 9]);
+/// ```
+#[cfg(feature = "std")]
+pub fn quicksort_with_pivots<T: Ord>(
+    slice: &mut [T],
+    pivots: &mut dyn Iterator<Item = usize>,
+) {
+    let nslice = slice.len();
+    if nslice <= 1 {
+        return;  // Nothing to sort.
+    }
+
+    // Partition around the requested element, or fall back
+    // to the usual machinery if the sequence is exhausted.
+    let pivot_index = match pivots.next() {
+        Some(choice) => partition_around(slice, choice.min(nslice - 1)),
+        None => partition(slice).unwrap(),
+    };
+
+    // Recurse on the two halves as usual.
+    quicksort_with_pivots(&mut slice[.. pivot_index], pivots);
+    quicksort_with_pivots(&mut slice[pivot_index + 1 ..], pivots);
+}
+
+#[test]
+fn quicksort_with_pivots_worst_case() {
+    // Always picking the first element of a sorted range is
+    // the classic worst case: every partition is maximally
+    // lopsided. The sort should still come out right.
+    let mut a: Vec<usize> = (0..100).collect();
+    let mut pivots = std::iter::repeat(0);
+    quicksort_with_pivots(&mut a, &mut pivots);
+    let expected: Vec<usize> = (0..100).collect();
+    assert_eq!(a, expected)
+}
+
+#[test]
+fn quicksort_with_pivots_exhausted() {
+    // Run out of pivots immediately and make sure the
+    // fallback still sorts.
+    let mut a = [9, 3, 7, 1, 5, 2, 8, 4, 6, 0];
+    let mut pivots = std::iter::empty();
+    quicksort_with_pivots(&mut a, &mut pivots);
+    assert_eq!(a, [0, 1, 2, 3, 4, 5, 6, 7, 8, 9])
+}
+
+// Comparator flavor of `partition_around()`: same Lomuto
+// scan, but ordering decisions come from `compare`.
+#[cfg(feature = "std")]
+fn partition_around_by<T, F>(slice: &mut [T], choice: usize, compare: &mut F) -> usize
+    where F: FnMut(&T, &T) -> Ordering
+{
+    let nslice = slice.len();
+    assert!(choice < nslice);
+    slice.swap(choice, nslice - 1);
+    let mut store = 0;
+    for i in 0..nslice - 1 {
+        if compare(&slice[i], &slice[nslice - 1]) != Ordering::Greater {
+            slice.swap(i, store);
+            store += 1
+        }
+    }
+    slice.swap(store, nslice - 1);
+    store
+}
+
+// Comparator flavor of `quicksort()`, used internally by
+// the entry points that sort non-`Ord` data. Picks the
+// middle element as pivot to dodge the sorted-input worst
+// case.
+#[cfg(feature = "std")]
+fn quicksort_by_compare<T, F>(slice: &mut [T], compare: &mut F)
+    where F: FnMut(&T, &T) -> Ordering
+{
+    let nslice = slice.len();
+    if nslice <= 1 {
+        return;  // Nothing to sort.
+    }
+    let pivot_index = partition_around_by(slice, nslice / 2, compare);
+    quicksort_by_compare(&mut slice[.. pivot_index], compare);
+    quicksort_by_compare(&mut slice[pivot_index + 1 ..], compare);
+}
+
+/// Sorts the elements of the slice into contiguous groups
+/// ordered by `discriminant`, then orders the elements
+/// within each group according to `within`. This is the
+/// usual two-level sort for heterogeneous collections:
+/// the discriminant decides which group an element lands
+/// in, and `within` only ever decides ties inside a group.
+///
+/// # Examples
+///
+/// ```
+/// use std::cmp::Ordering;
+/// let mut a = [(1, 'b'), (0, 'z'), (1, 'a'), (0, 'y')];
+/// quicksort::quicksort_grouped_by(
+///     &mut a,
+///     |t| t.0,
+///     |s, t| s.1.cmp(&t.1),
+/// );
+/// assert_eq!(a, [(0, 'y'), (0, 'z'), (1, 'a'), (1, 'b')]);
+/// ```
+#[cfg(feature = "std")]
+pub fn quicksort_grouped_by<T, D: Ord>(
+    slice: &mut [T],
+    discriminant: impl Fn(&T) -> D,
+    mut within: impl FnMut(&T, &T) -> Ordering,
+) {
+    // A single pass with a composite comparator gives both
+    // levels at once: groups come out contiguous because
+    // the discriminant dominates.
+    quicksort_by_compare(slice, &mut |a: &T, b: &T| {
+        match discriminant(a).cmp(&discriminant(b)) {
+            Ordering::Equal => within(a, b),
+            unequal => unequal,
+        }
+    })
+}
+
+#[test]
+fn quicksort_grouped_by_two_levels() {
+    // Group 'a' sorts ascending by value, group 'b'
+    // descending, exercising a `within` that keys off the
+    // group it finds itself in.
+    let mut a = [
+        ('b', 1), ('a', 3), ('b', 3), ('a', 1), ('b', 2), ('a', 2),
+    ];
+    quicksort_grouped_by(
+        &mut a,
+        |t| t.0,
+        |s, t| if s.0 == 'a' { s.1.cmp(&t.1) } else { t.1.cmp(&s.1) },
+    );
+    assert_eq!(a, [
+        ('a', 1), ('a', 2), ('a', 3), ('b', 3), ('b', 2), ('b', 1),
+    ])
+}
+
+// Recursive worker for `quicksort_comparison_histogram()`.
+// `counts` rides along with `slice`: every swap is
+// mirrored, so counts[i] stays attached to the element at
+// slice[i], and both sides of every comparison get
+// credited.
+#[cfg(feature = "std")]
+fn histogram_sort<T: Ord>(slice: &mut [T], counts: &mut [u32]) {
+    let nslice = slice.len();
+    if nslice <= 1 {
+        return;  // Nothing to sort.
+    }
+
+    // Same middle-pivot Lomuto scan as
+    // `quicksort_by_compare()`, with bookkeeping.
+    slice.swap(nslice / 2, nslice - 1);
+    counts.swap(nslice / 2, nslice - 1);
+    let mut store = 0;
+    for i in 0..nslice - 1 {
+        counts[i] += 1;
+        counts[nslice - 1] += 1;
+        if slice[i] <= slice[nslice - 1] {
+            slice.swap(i, store);
+            counts.swap(i, store);
+            store += 1
+        }
+    }
+    slice.swap(store, nslice - 1);
+    counts.swap(store, nslice - 1);
+
+    histogram_sort(&mut slice[.. store], &mut counts[.. store]);
+    histogram_sort(&mut slice[store + 1 ..], &mut counts[store + 1 ..]);
+}
+
+/// Sorts the elements of the slice and returns, aligned to
+/// the final sorted positions, the number of comparisons
+/// each element participated in during the sort. Both
+/// parties to a comparison are credited, so the histogram
+/// sums to twice the total comparison count. Useful for
+/// fairness analysis in comparison-based ranking, where it
+/// shows which elements were examined most.
+///
+/// # Examples
+///
+/// ```
+/// let mut a = [3, 1, 2];
+/// let counts = quicksort::quicksort_comparison_histogram(&mut a);
+/// assert_eq!(a, [1, 2, 3]);
+/// assert_eq!(counts.iter().sum::<u32>() % 2, 0);
+/// ```
+#[cfg(feature = "std")]
+pub fn quicksort_comparison_histogram<T: Ord>(slice: &mut [T]) -> Vec<u32> {
+    let mut counts = vec![0; slice.len()];
+    histogram_sort(slice, &mut counts);
+    counts
+}
+
+#[test]
+fn quicksort_comparison_histogram_total() {
+    let a = [5, 1, 0, 2, 2, 4, 3, 2, 9, 7];
+
+    // Count total comparisons with the comparator-based
+    // sort, which uses the identical pivot strategy and
+    // scan, so the counts must line up.
+    let mut b = a;
+    let mut ncompares = 0u32;
+    quicksort_by_compare(&mut b, &mut |s: &i32, t: &i32| {
+        ncompares += 1;
+        s.cmp(t)
+    });
+
+    let mut a = a;
+    let counts = quicksort_comparison_histogram(&mut a);
+    assert_eq!(a, b);
+    assert_eq!(counts.iter().sum::<u32>(), 2 * ncompares)
+}
+
+/// Sorts the elements of a slice of `PartialOrd` values,
+/// falling back to `tiebreak` for pairs that
+/// `partial_cmp()` declares incomparable (`None`), such as
+/// a float compared against NaN. This gives well-defined
+/// behavior on inputs that would make an `unwrap()`-based
+/// comparator panic; the `tiebreak` decides where the
+/// incomparable values go.
+///
+/// # Examples
+///
+/// ```
+/// use std::cmp::Ordering;
+/// let mut a = [2.0, 1.0, 3.0];
+/// quicksort::quicksort_partial_with_tiebreak(
+///     &mut a,
+///     |_, _| Ordering::Equal,
+/// );
+/// assert_eq!(a, [1.0, 2.0, 3.0]);
+/// ```
+#[cfg(feature = "std")]
+pub fn quicksort_partial_with_tiebreak<T: PartialOrd>(
+    slice: &mut [T],
+    mut tiebreak: impl FnMut(&T, &T) -> Ordering,
+) {
+    quicksort_by_compare(slice, &mut |a: &T, b: &T| {
+        match a.partial_cmp(b) {
+            Some(ordering) => ordering,
+            None => tiebreak(a, b),
+        }
+    })
+}
+
+#[test]
+fn quicksort_partial_with_tiebreak_nan_last() {
+    let mut a = [3.0, f64::NAN, 1.0, 2.0, f64::NAN, 0.5];
+
+    // Order NaNs after everything else, and leave NaN-NaN
+    // pairs tied.
+    quicksort_partial_with_tiebreak(&mut a, |s, t| {
+        match (s.is_nan(), t.is_nan()) {
+            (true, false) => Ordering::Greater,
+            (false, true) => Ordering::Less,
+            _ => Ordering::Equal,
+        }
+    });
+
+    assert_eq!(&a[..4], &[0.5, 1.0, 2.0, 3.0]);
+    assert!(a[4].is_nan());
+    assert!(a[5].is_nan())
+}
+
+/// Removes elements whose key has been seen earlier in the
+/// vector (so the first occurrence of each key survives),
+/// then sorts the survivors with `quicksort()`. The dedup
+/// phase is a single left-to-right pass using a hash set of
+/// seen keys, expected `O(n)`; the sort phase is the usual
+/// expected `O(n log n)`.
+///
+/// # Examples
+///
+/// ```
+/// let mut v = vec![3, 1, 3, 2, 1];
+/// quicksort::dedup_first_then_sort(&mut v, |&x| x);
+/// assert_eq!(v, [1, 2, 3]);
+/// ```
+#[cfg(feature = "std")]
+pub fn dedup_first_then_sort<T: Ord, K: Ord + std::hash::Hash>(
+    vec: &mut Vec<T>,
+    mut key: impl FnMut(&T) -> K,
+) {
+    // Phase 1: keep only the earliest element bearing each
+    // key.
+    let mut seen = std::collections::HashSet::new();
+    vec.retain(|v| seen.insert(key(v)));
+
+    // Phase 2: sort what's left.
+    quicksort(vec)
+}
+
+#[test]
+fn dedup_first_then_sort_keeps_earliest() {
+    // Key on the letter; the digit marks which occurrence
+    // each element was.
+    let mut v = vec![
+        ('c', 0), ('a', 0), ('c', 1), ('b', 0), ('a', 1), ('c', 2),
+    ];
+    dedup_first_then_sort(&mut v, |t| t.0);
+    assert_eq!(v, [('a', 0), ('b', 0), ('c', 0)])
+}
+
+// Recursive worker for `quicksort_swap_plan()`. `shadow`
+// holds, for each virtual position, the index into `slice`
+// of the element currently living there; swapping shadow
+// entries stands in for swapping the real data, and every
+// effective swap is appended to `plan` in absolute
+// coordinates (`base` is the offset of this subrange).
+#[cfg(feature = "std")]
+fn plan_sort<T: Ord>(
+    slice: &[T],
+    shadow: &mut [usize],
+    base: u32,
+    plan: &mut Vec<(u32, u32)>,
+) {
+    let nslice = shadow.len();
+    if nslice <= 1 {
+        return;  // Nothing to sort.
+    }
+
+    // Record a swap, skipping no-ops so the plan stays
+    // minimal.
+    macro_rules! planned_swap {
+        ($i:expr, $j:expr) => {{
+            let (i, j) = ($i, $j);
+            if i != j {
+                shadow.swap(i, j);
+                plan.push((base + i as u32, base + j as u32))
+            }
+        }}
+    }
+
+    // The usual middle-pivot Lomuto scan, done virtually.
+    planned_swap!(nslice / 2, nslice - 1);
+    let mut store = 0;
+    for i in 0..nslice - 1 {
+        if slice[shadow[i]] <= slice[shadow[nslice - 1]] {
+            planned_swap!(i, store);
+            store += 1
+        }
+    }
+    planned_swap!(store, nslice - 1);
+
+    plan_sort(slice, &mut shadow[.. store], base, plan);
+    plan_sort(
+        slice,
+        &mut shadow[store + 1 ..],
+        base + store as u32 + 1,
+        plan,
+    );
+}
+
+/// Computes the sequence of index swaps that would sort
+/// `slice`, without mutating it, returning the swaps as
+/// `u32` pairs suitable for uploading to a compute shader:
+/// the CPU does the planning, the GPU does the moving.
+/// Applying the swaps in order to a copy of the input
+/// yields that copy sorted ascending. No-op swaps are
+/// omitted from the plan.
+///
+/// # Examples
+///
+/// ```
+/// let a = [3, 1, 2];
+/// let plan = quicksort::quicksort_swap_plan(&a);
+/// let mut b = a;
+/// for (i, j) in plan {
+///     b.swap(i as usize, j as usize)
+/// }
+/// assert_eq!(b, [1, 2, 3]);
+/// ```
+#[cfg(feature = "std")]
+pub fn quicksort_swap_plan<T: Ord>(slice: &[T]) -> Vec<(u32, u32)> {
+    assert!(slice.len() <= u32::MAX as usize);
+    let mut shadow: Vec<usize> = (0..slice.len()).collect();
+    let mut plan = Vec::new();
+    plan_sort(slice, &mut shadow, 0, &mut plan);
+    plan
+}
+
+#[test]
+fn quicksort_swap_plan_applies() {
+    let a = [5, 1, 0, 2, 2, 4, 3, 2, 9, 7, 6, 8];
+    let plan = quicksort_swap_plan(&a);
+
+    // The input must not have been touched.
+    assert_eq!(a, [5, 1, 0, 2, 2, 4, 3, 2, 9, 7, 6, 8]);
+
+    // Play the plan back against a copy.
+    let mut b = a;
+    for (i, j) in plan {
+        b.swap(i as usize, j as usize)
+    }
+    assert_eq!(b, [0, 1, 2, 2, 2, 3, 4, 5, 6, 7, 8, 9])
+}
+
+/// Sorts the slice into ascending order, but treats values
+/// within `epsilon` of each other as equal during
+/// partitioning, so near-ties are not shuffled about
+/// unnecessarily. Epsilon-equality is famously
+/// intransitive (`a ≈ b` and `b ≈ c` do not give `a ≈ c`),
+/// which can break a sort that uses it between arbitrary
+/// pairs; here every comparison in a partition step is
+/// made against the single pivot representative, so each
+/// step sees a consistent ordering and the recursion stays
+/// well behaved. The result is ascending up to `epsilon`:
+/// each element exceeds its predecessor by at most
+/// `epsilon` less than a true sort would demand.
+///
+/// NaNs are treated as equal to everything and so end up
+/// in arbitrary positions; filter them first if that
+/// matters.
+///
+/// # Examples
+///
+/// ```
+/// let mut a = [3.0, 1.0, 2.0];
+/// quicksort::quicksort_approx(&mut a, 0.001);
+/// assert_eq!(a, [1.0, 2.0, 3.0]);
+/// ```
+#[cfg(feature = "std")]
+pub fn quicksort_approx(slice: &mut [f64], epsilon: f64) {
+    quicksort_by_compare(slice, &mut |a: &f64, b: &f64| {
+        if (a - b).abs() <= epsilon {
+            Ordering::Equal
+        } else if a < b {
+            Ordering::Less
+        } else {
+            Ordering::Greater
+        }
+    })
+}
+
+#[test]
+fn quicksort_approx_clusters() {
+    // Two clusters of values within epsilon of their
+    // neighbors, interleaved.
+    let mut a = [10.0, 1.01, 10.02, 0.99, 10.01, 1.0, 1.02, 10.03];
+    let epsilon = 0.05;
+    quicksort_approx(&mut a, epsilon);
+
+    // The order must be ascending up to epsilon slop.
+    for i in 1..a.len() {
+        assert!(a[i - 1] <= a[i] + epsilon,
+                "disorder at {}: {:?}", i, a)
+    }
+
+    // The low cluster must precede the high cluster.
+    for v in &a[..4] {
+        assert!(*v < 2.0)
+    }
+    for v in &a[4..] {
+        assert!(*v > 9.0)
+    }
+}
+
+/// One node of the divide-and-conquer shape captured by
+/// `quicksort_recursion_tree()`. The node covers the
+/// half-open range `start..end` of the original slice. An
+/// internal node records the absolute index its pivot
+/// landed at and has exactly two children (the subranges
+/// below and above the pivot); a base-case node (range of
+/// length at most one) has no pivot and no children.
+#[derive(Debug)]
+#[cfg(feature = "std")]
+pub struct RecursionNode {
+    /// First index of the range this call partitioned.
+    pub start: usize,
+    /// One past the last index of the range.
+    pub end: usize,
+    /// Absolute index where the pivot settled, if this
+    /// call partitioned at all.
+    pub pivot: Option<usize>,
+    /// Child calls, in slice order: empty for a base case,
+    /// two entries otherwise.
+    pub children: Vec<RecursionNode>,
+}
+
+// Recursive worker for `quicksort_recursion_tree()`.
+// `start` is the absolute offset of `slice` within the
+// original.
+#[cfg(feature = "std")]
+fn recursion_tree_sort<T: Ord>(slice: &mut [T], start: usize) -> RecursionNode {
+    let nslice = slice.len();
+    if nslice <= 1 {
+        return RecursionNode {
+            start,
+            end: start + nslice,
+            pivot: None,
+            children: Vec::new(),
+        }
+    }
+
+    let pivot_index = partition(slice).unwrap();
+    let low = recursion_tree_sort(&mut slice[.. pivot_index], start);
+    let high = recursion_tree_sort(
+        &mut slice[pivot_index + 1 ..],
+        start + pivot_index + 1,
+    );
+    RecursionNode {
+        start,
+        end: start + nslice,
+        pivot: Some(start + pivot_index),
+        children: vec![low, high],
+    }
+}
+
+/// Sorts the elements of the slice like `quicksort()` and
+/// returns a `RecursionNode` tree capturing each
+/// partition's range, where its pivot landed, and the two
+/// child subranges. This records the divide-and-conquer
+/// shape of the run (as opposed to, say, a swap trace), so
+/// a teaching front-end can render the recursion
+/// structure.
+#[cfg(feature = "std")]
+pub fn quicksort_recursion_tree<T: Ord + Clone>(slice: &mut [T]) -> RecursionNode {
+    recursion_tree_sort(slice, 0)
+}
+
+#[test]
+fn quicksort_recursion_tree_shape() {
+    fn check(node: &RecursionNode) {
+        assert!(node.start <= node.end);
+        if node.children.is_empty() {
+            // Base case: nothing was partitioned.
+            assert!(node.end - node.start <= 1);
+            assert!(node.pivot.is_none())
+        } else {
+            assert_eq!(node.children.len(), 2);
+            let pivot = node.pivot.unwrap();
+            assert!(node.start <= pivot && pivot < node.end);
+            assert_eq!(node.children[0].start, node.start);
+            assert_eq!(node.children[0].end, pivot);
+            assert_eq!(node.children[1].start, pivot + 1);
+            assert_eq!(node.children[1].end, node.end);
+            for child in &node.children {
+                check(child)
+            }
+        }
+    }
+
+    let mut a = [5, 1, 0, 2, 2, 4, 3, 2, 9, 7];
+    let n = a.len();
+    let root = quicksort_recursion_tree(&mut a);
+    assert_eq!(a, [0, 1, 2, 2, 2, 3, 4, 5, 7, 9]);
+    assert_eq!(root.start, 0);
+    assert_eq!(root.end, n);
+    check(&root)
+}
+
+/// Repairs the order of a slice that was fully sorted
+/// except that the element at `changed_index` has since
+/// been modified. The stray element is shifted to its
+/// correct position by a bounded insertion, costing
+/// `O(distance)` moves rather than a full re-sort.
+///
+/// The precondition matters: every element other than the
+/// one at `changed_index` must already be in sorted order
+/// relative to the others, or the result is unspecified
+/// (though no worse than unsorted).
+///
+/// # Examples
+///
+/// ```
+/// let mut a = [1, 2, 9, 4, 5];  // was [1,2,3,4,5], 3 -> 9
+/// quicksort::resort_after_change(&mut a, 2);
+/// assert_eq!(a, [1, 2, 4, 5, 9]);
+/// ```
+#[cfg(feature = "std")]
+pub fn resort_after_change<T: Ord>(slice: &mut [T], changed_index: usize) {
+    let nslice = slice.len();
+    assert!(changed_index < nslice);
+    let mut i = changed_index;
+
+    // Bubble the changed element toward the front if it
+    // shrank…
+    while i > 0 && slice[i - 1] > slice[i] {
+        slice.swap(i - 1, i);
+        i -= 1
+    }
+
+    // …or toward the back if it grew. At most one of these
+    // loops runs.
+    while i + 1 < nslice && slice[i] > slice[i + 1] {
+        slice.swap(i, i + 1);
+        i += 1
+    }
+}
+
+#[test]
+fn resort_after_change_both_directions() {
+    // Element grew.
+    let mut a = [0, 1, 7, 3, 4, 5, 6];
+    resort_after_change(&mut a, 2);
+    assert_eq!(a, [0, 1, 3, 4, 5, 6, 7]);
+
+    // Element shrank.
+    let mut a = [0, 1, 2, 3, 4, 5, -1];
+    resort_after_change(&mut a, 6);
+    assert_eq!(a, [-1, 0, 1, 2, 3, 4, 5]);
+
+    // Element didn't move at all.
+    let mut a = [0, 1, 2, 3];
+    resort_after_change(&mut a, 1);
+    assert_eq!(a, [0, 1, 2, 3])
+}
+
+// Recursive worker for `quicksort_strings_msd()`. Sorts
+// the strings by their bytes from position `depth`
+// onward, assuming all of them agree on the first `depth`
+// bytes. Strings that end at `depth` sort before any that
+// continue.
+#[cfg(feature = "std")]
+fn msd_sort(slice: &mut [&str], depth: usize) {
+    let nslice = slice.len();
+    if nslice <= 1 {
+        return;  // Nothing to sort.
+    }
+
+    // The byte at `depth`, with "string ended" sorting
+    // first. `Option<u8>` gets exactly that ordering for
+    // free.
+    let byte_at = |s: &str| -> Option<u8> {
+        s.as_bytes().get(depth).cloned()
+    };
+
+    // Three-way partition on the middle element's byte:
+    // [.. lt] below the pivot byte, [lt .. gt] equal to
+    // it, [gt ..] above it.
+    let pivot = byte_at(slice[nslice / 2]);
+    let mut lt = 0;
+    let mut i = 0;
+    let mut gt = nslice;
+    while i < gt {
+        match byte_at(slice[i]).cmp(&pivot) {
+            Ordering::Less => {
+                slice.swap(lt, i);
+                lt += 1;
+                i += 1
+            },
+            Ordering::Greater => {
+                gt -= 1;
+                slice.swap(i, gt)
+            },
+            Ordering::Equal => i += 1,
+        }
+    }
+
+    // The outer bands stay at this depth; the middle band
+    // agrees on one more byte, so it descends — unless the
+    // shared byte was end-of-string, in which case those
+    // strings are all equal and done.
+    msd_sort(&mut slice[.. lt], depth);
+    if pivot.is_some() {
+        msd_sort(&mut slice[lt .. gt], depth + 1)
+    }
+    msd_sort(&mut slice[gt ..], depth);
+}
+
+/// Sorts a slice of strings into lexicographic (byte)
+/// order using multikey quicksort: a three-way partition
+/// on the byte at the current depth, with the equal band
+/// recursing at depth + 1 and the outer bands staying at
+/// the same depth. For string-heavy data with shared
+/// prefixes this beats a plain comparison sort, since each
+/// prefix byte is examined once per band rather than once
+/// per comparison.
+///
+/// # Examples
+///
+/// ```
+/// let mut a = ["banana", "apple", "cherry"];
+/// quicksort::quicksort_strings_msd(&mut a);
+/// assert_eq!(a, ["apple", "banana", "cherry"]);
+/// ```
+#[cfg(feature = "std")]
+pub fn quicksort_strings_msd(slice: &mut [&str]) {
+    msd_sort(slice, 0)
+}
+
+#[test]
+fn quicksort_strings_msd_shared_prefixes() {
+    let mut a = [
+        "apply", "app", "apple", "banana", "", "band", "ban",
+        "applesauce", "a", "apple",
+    ];
+    quicksort_strings_msd(&mut a);
+    assert_eq!(a, [
+        "", "a", "app", "apple", "apple", "applesauce", "apply",
+        "ban", "banana", "band",
+    ])
+}
+
+/// Random-access data that quicksort can operate on
+/// without a native slice: all the sort needs is a length,
+/// element reads, and swaps. Reads return an owned copy of
+/// the element, since the backing store (say, a
+/// memory-mapped file) may not be able to hand out
+/// references.
+#[cfg(feature = "std")]
+pub trait IndexedData {
+    /// The element type being sorted.
+    type Item: Ord;
+
+    /// Number of elements.
+    fn len(&self) -> usize;
+
+    /// Whether there are no elements.
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Read out a copy of the element at `index`.
+    fn get(&self, index: usize) -> Self::Item;
+
+    /// Exchange the elements at `i` and `j`.
+    fn swap(&mut self, i: usize, j: usize);
+}
+
+/// A fixed-size record that can be encoded into and
+/// decoded from a run of `SIZE` bytes, for storage in a
+/// flat byte buffer.
+#[cfg(feature = "std")]
+pub trait Record: Ord {
+    /// Encoded size of every record, in bytes.
+    const SIZE: usize;
+
+    /// Decode a record from exactly `SIZE` bytes.
+    fn read_record(bytes: &[u8]) -> Self;
+
+    /// Encode this record into exactly `SIZE` bytes.
+    fn write_record(&self, bytes: &mut [u8]);
+}
+
+/// A byte region — typically an `mmap`'d file, though any
+/// `&mut [u8]` will do — viewed as a sequence of
+/// fixed-size `Record`s at computed offsets. Record `i`
+/// occupies bytes `i * R::SIZE .. (i + 1) * R::SIZE`; the
+/// buffer length must be a whole number of records. No
+/// alignment is required of the region, since records are
+/// copied bytewise through `read_record()` /
+/// `write_record()` rather than transmuted in place.
+#[cfg(feature = "std")]
+pub struct MmapSlice<'a, R: Record> {
+    bytes: &'a mut [u8],
+    _record: std::marker::PhantomData<R>,
+}
+
+#[cfg(feature = "std")]
+impl<'a, R: Record> MmapSlice<'a, R> {
+    /// Wrap a byte region holding a whole number of
+    /// records.
+    pub fn new(bytes: &'a mut [u8]) -> MmapSlice<'a, R> {
+        assert_eq!(bytes.len() % R::SIZE, 0,
+                   "byte region is not a whole number of records");
+        MmapSlice {
+            bytes,
+            _record: std::marker::PhantomData,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<'a, R: Record> IndexedData for MmapSlice<'a, R> {
+    type Item = R;
+
+    fn len(&self) -> usize {
+        self.bytes.len() / R::SIZE
+    }
+
+    fn get(&self, index: usize) -> R {
+        let start = index * R::SIZE;
+        R::read_record(&self.bytes[start .. start + R::SIZE])
+    }
+
+    fn swap(&mut self, i: usize, j: usize) {
+        if i == j {
+            return
+        }
+        // Records are small and unaligned, so a bytewise
+        // exchange is the simple safe choice.
+        for k in 0..R::SIZE {
+            self.bytes.swap(i * R::SIZE + k, j * R::SIZE + k)
+        }
+    }
+}
+
+// Recursive worker for `quicksort_indexed()`, sorting the
+// half-open index range `lo..hi`. Middle-pivot Lomuto, as
+// in `quicksort_by_compare()`, but phrased in terms of the
+// trait's reads and swaps.
+#[cfg(feature = "std")]
+fn indexed_sort<D: IndexedData>(data: &mut D, lo: usize, hi: usize) {
+    let nrange = hi - lo;
+    if nrange <= 1 {
+        return;  // Nothing to sort.
+    }
+
+    data.swap(lo + nrange / 2, hi - 1);
+    let pivot = data.get(hi - 1);
+    let mut store = lo;
+    for i in lo..hi - 1 {
+        if data.get(i) <= pivot {
+            data.swap(i, store);
+            store += 1
+        }
+    }
+    data.swap(store, hi - 1);
+
+    indexed_sort(data, lo, store);
+    indexed_sort(data, store + 1, hi);
+}
+
+/// Sorts any `IndexedData` in place through its reads and
+/// swaps.
+#[cfg(feature = "std")]
+pub fn quicksort_indexed<D: IndexedData>(data: &mut D) {
+    let ndata = data.len();
+    indexed_sort(data, 0, ndata)
+}
+
+/// Sorts the fixed-size records of a (typically
+/// memory-mapped) byte region in place, letting the crate
+/// sort larger-than-RAM files. See `MmapSlice` for the
+/// record layout requirements.
+#[cfg(feature = "std")]
+pub fn quicksort_mmap<R: Record>(map: &mut MmapSlice<R>) {
+    quicksort_indexed(map)
+}
+
+#[test]
+fn quicksort_mmap_records() {
+    // A little-endian u16 record standing in for a real
+    // file record.
+    #[derive(PartialEq, Eq, PartialOrd, Ord)]
+    struct U16Record(u16);
+
+    impl Record for U16Record {
+        const SIZE: usize = 2;
+
+        fn read_record(bytes: &[u8]) -> U16Record {
+            U16Record(u16::from(bytes[0]) | u16::from(bytes[1]) << 8)
+        }
+
+        fn write_record(&self, bytes: &mut [u8]) {
+            bytes[0] = self.0 as u8;
+            bytes[1] = (self.0 >> 8) as u8
+        }
+    }
+
+    // Build an in-memory "file" of records, scrambled.
+    let values = [513u16, 2, 770, 256, 1, 258, 0, 515];
+    let mut bytes = vec![0u8; values.len() * 2];
+    for (i, v) in values.iter().enumerate() {
+        U16Record(*v).write_record(&mut bytes[i * 2 .. i * 2 + 2])
+    }
+
+    let mut map: MmapSlice<U16Record> = MmapSlice::new(&mut bytes);
+    quicksort_mmap(&mut map);
+
+    let mut expected = values;
+    quicksort(&mut expected);
+    for (i, v) in expected.iter().enumerate() {
+        assert_eq!(map.get(i).0, *v)
+    }
+}
+
+// Recursive worker for `quicksort_anytime()`. Each
+// partition of an `n`-element range is charged `n - 1`
+// comparisons against the budget; a range whose partition
+// won't fit in the remaining budget is left as-is.
+// `resolved` counts elements known to be in their final
+// position.
+#[cfg(feature = "std")]
+fn anytime_sort<T: Ord>(slice: &mut [T], budget: &mut u64, resolved: &mut usize) {
+    let nslice = slice.len();
+    if nslice <= 1 {
+        // Trivially in final position.
+        *resolved += nslice;
+        return
+    }
+
+    // Out of budget: leave this range in whatever partial
+    // order it has.
+    let cost = (nslice - 1) as u64;
+    if *budget < cost {
+        return
+    }
+    *budget -= cost;
+
+    let pivot_index = partition(slice).unwrap();
+    *resolved += 1;  // The pivot is final.
+    anytime_sort(&mut slice[.. pivot_index], budget, resolved);
+    anytime_sort(&mut slice[pivot_index + 1 ..], budget, resolved);
+}
+
+/// Sorts as much of the slice as a budget of roughly
+/// `budget` comparisons allows, and returns an estimate in
+/// `[0, 1]` of how sorted the result is: the fraction of
+/// elements known to have reached their final position.
+/// Ranges whose partition would overrun the budget are
+/// left untouched, so the result is always at least
+/// partially ordered (every completed pivot correctly
+/// separates its neighbors) and a larger budget never
+/// yields a smaller estimate. This suits real-time callers
+/// that want "as sorted as possible within the time
+/// available"; contrast a hard-abort budgeted sort, which
+/// gives no progress report.
+///
+/// # Examples
+///
+/// ```
+/// let mut a = [3, 1, 2];
+/// let progress = quicksort::quicksort_anytime(&mut a, 1_000);
+/// assert_eq!(progress, 1.0);
+/// assert_eq!(a, [1, 2, 3]);
+/// ```
+#[cfg(feature = "std")]
+pub fn quicksort_anytime<T: Ord>(slice: &mut [T], budget: u64) -> f64 {
+    let nslice = slice.len();
+    if nslice == 0 {
+        // An empty slice is as sorted as it gets.
+        return 1.0
+    }
+    let mut budget = budget;
+    let mut resolved = 0;
+    anytime_sort(slice, &mut budget, &mut resolved);
+    resolved as f64 / nslice as f64
+}
+
+#[test]
+fn quicksort_anytime_progress_monotone() {
+    use rand::Rng;
+    let mut a = Vec::with_capacity(200);
+    for _ in 0..200 {
+        a.push(rand::thread_rng().gen_range(-1000, 1000))
+    }
+
+    // Progress should climb (weakly) with budget, reaching
+    // 1.0 once the budget stops binding.
+    let mut last = 0.0;
+    for budget in &[0u64, 10, 50, 200, 1000, 100_000] {
+        let mut b = a.clone();
+        let progress = quicksort_anytime(&mut b, *budget);
+        assert!((0.0..=1.0).contains(&progress));
+        assert!(progress >= last,
+                "progress fell from {} to {} at budget {}",
+                last, progress, budget);
+        last = progress
+    }
+    assert_eq!(last, 1.0);
+
+    // And the unlimited run must actually sort.
+    quicksort_anytime(&mut a, u64::MAX);
+    for i in 1..a.len() {
+        assert!(a[i - 1] <= a[i])
+    }
+}
+
+/// Sorts the slice, then folds each run of equal elements
+/// into a fresh accumulator from `init`, returning one
+/// accumulator per group in sorted-key order. Runs are
+/// detected by walking the sorted slice and comparing each
+/// element to the previous one: `Ordering::Equal` extends
+/// the current group, anything else starts a new one. This
+/// is a one-pass group-by built on the sort.
+///
+/// # Examples
+///
+/// ```
+/// let mut a = [2, 1, 2, 1, 1];
+/// let counts = quicksort::quicksort_group_aggregate(
+///     &mut a,
+///     || 0,
+///     |n, _| *n += 1,
+/// );
+/// assert_eq!(counts, [3, 2]);
+/// ```
+#[cfg(feature = "std")]
+pub fn quicksort_group_aggregate<T: Ord, A>(
+    slice: &mut [T],
+    init: impl Fn() -> A,
+    mut fold: impl FnMut(&mut A, &T),
+) -> Vec<A> {
+    quicksort(slice);
+
+    let mut groups = Vec::new();
+    for i in 0..slice.len() {
+        if i == 0 || slice[i - 1].cmp(&slice[i]) != Ordering::Equal {
+            // A new run of equal elements starts here.
+            groups.push(init())
+        }
+        let ngroups = groups.len();
+        fold(&mut groups[ngroups - 1], &slice[i])
+    }
+    groups
+}
+
+#[test]
+fn quicksort_group_aggregate_sums() {
+    // Key-value pairs ordered (and grouped) by key alone.
+    struct Kv(u32, u32);
+
+    impl PartialEq for Kv {
+        fn eq(&self, other: &Kv) -> bool {
+            self.cmp(other) == Ordering::Equal
+        }
+    }
+
+    impl Eq for Kv {}
+
+    impl PartialOrd for Kv {
+        fn partial_cmp(&self, other: &Kv) -> Option<Ordering> {
+            Some(self.cmp(other))
+        }
+    }
+
+    impl Ord for Kv {
+        fn cmp(&self, other: &Kv) -> Ordering {
+            self.0.cmp(&other.0)
+        }
+    }
+
+    let mut a = [Kv(2, 10), Kv(1, 1), Kv(2, 20), Kv(3, 5), Kv(1, 2)];
+    let sums = quicksort_group_aggregate(
+        &mut a,
+        || 0,
+        |sum, kv| *sum += kv.1,
+    );
+    assert_eq!(sums, [3, 30, 5])
+}
+
+/// Sorts a slice of floats ascending, but first scans for
+/// NaN and refuses to proceed if one is present, returning
+/// the index of the first NaN as the error. This is the
+/// fail-fast alternative to total-ordering approaches
+/// (total-cmp bit tricks or a NaN-last comparator): rather
+/// than silently placing NaNs somewhere, the caller is
+/// told exactly where the bad value is. On `Ok(())` the
+/// slice is fully sorted.
+///
+/// # Examples
+///
+/// ```
+/// let mut a = [2.0, 1.0, 3.0];
+/// assert_eq!(quicksort::quicksort_floats_checked(&mut a), Ok(()));
+/// assert_eq!(a, [1.0, 2.0, 3.0]);
+///
+/// let mut b = [2.0, f64::NAN, 3.0];
+/// assert_eq!(quicksort::quicksort_floats_checked(&mut b), Err(1));
+/// ```
+#[cfg(feature = "std")]
+pub fn quicksort_floats_checked(slice: &mut [f64]) -> Result<(), usize> {
+    // Pre-check: find any NaN before disturbing the data.
+    for (i, v) in slice.iter().enumerate() {
+        if v.is_nan() {
+            return Err(i)
+        }
+    }
+
+    // NaN-free floats are totally ordered, so the unwrap
+    // cannot fire.
+    quicksort_by_compare(slice, &mut |a: &f64, b: &f64| {
+        a.partial_cmp(b).unwrap()
+    });
+    Ok(())
+}
+
+#[test]
+fn quicksort_floats_checked_reports_nan() {
+    let mut a = [3.0, 0.5, f64::NAN, 1.0, f64::NAN];
+    assert_eq!(quicksort_floats_checked(&mut a), Err(2));
+    // The failed call must not have reordered anything.
+    assert_eq!(&a[..2], &[3.0, 0.5]);
+
+    let mut b = [3.0, 0.5, 2.5, 1.0, -0.0];
+    assert_eq!(quicksort_floats_checked(&mut b), Ok(()));
+    assert_eq!(b, [-0.0, 0.5, 1.0, 2.5, 3.0])
+}
+
+/// Sorts the slice by `key` and returns a lookup index: a
+/// `Vec` of `(key, final_position)` pairs, itself sorted
+/// by key, so a binary search on the index finds the
+/// position of a key's element in the sorted slice. This
+/// fuses sorting and index construction into one call.
+/// Equal keys occupy adjacent positions in both the slice
+/// and the index.
+///
+/// # Examples
+///
+/// ```
+/// let mut a = ["bb", "a", "cc"];
+/// let index = quicksort::quicksort_indexed_by_key(
+///     &mut a,
+///     |s| s.len(),
+/// );
+/// assert_eq!(index, [(1, 0), (2, 1), (2, 2)]);
+/// ```
+#[cfg(feature = "std")]
+pub fn quicksort_indexed_by_key<T, K: Ord + Clone>(
+    slice: &mut [T],
+    mut key: impl FnMut(&T) -> K,
+) -> Vec<(K, usize)> {
+    // Sort by the extracted key.
+    quicksort_by_compare(slice, &mut |a: &T, b: &T| key(a).cmp(&key(b)));
+
+    // One more pass reads the keys off in final order; the
+    // index inherits sortedness from the slice.
+    slice
+        .iter()
+        .enumerate()
+        .map(|(i, v)| (key(v), i))
+        .collect()
+}
+
+#[test]
+fn quicksort_indexed_by_key_lookup() {
+    let mut a = [(30, "thirty"), (10, "ten"), (50, "fifty"), (20, "twenty")];
+    let index = quicksort_indexed_by_key(&mut a, |t| t.0);
+
+    // The index must be sorted by key and point at the
+    // right slots.
+    for i in 1..index.len() {
+        assert!(index[i - 1].0 <= index[i].0)
+    }
+
+    // Use it for a binary-search lookup.
+    let slot = index.binary_search_by_key(&20, |e| e.0).unwrap();
+    let position = index[slot].1;
+    assert_eq!(a[position], (20, "twenty"))
+}
+
+// A small xorshift64 generator seeded from the standard
+// library's `RandomState`, so the crate gets throwaway
+// randomness without a runtime dependency on `rand` (which
+// is dev-only here).
+#[cfg(feature = "std")]
+struct CheapRng(u64);
+
+#[cfg(feature = "std")]
+impl CheapRng {
+    fn new() -> CheapRng {
+        use std::hash::{BuildHasher, Hasher};
+        let mut h = std::collections::hash_map::RandomState::new()
+            .build_hasher();
+        h.write_u64(0x9e37_79b9_7f4a_7c15);
+        // Xorshift must not start at zero.
+        CheapRng(h.finish() | 1)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    // Uniform in [0, 1).
+    fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+/// Fully sorts the slice, then swaps each adjacent pair
+/// independently with probability `disorder` (clamped to
+/// `[0, 1]`), left to right. The result has a known,
+/// tunable level of sortedness for benchmarking adaptive
+/// sorts: with `d = disorder` and `n` elements, each of
+/// the `n - 1` adjacent swaps of distinct values creates
+/// one inversion, so expect about `d * (n - 1)` inversions
+/// (a little more when consecutive swaps compound). At
+/// `disorder = 0` the slice is left perfectly sorted.
+#[cfg(feature = "std")]
+pub fn quicksort_partial_disorder<T: Ord>(slice: &mut [T], disorder: f64) {
+    quicksort(slice);
+
+    if disorder <= 0.0 {
+        return
+    }
+    let mut rng = CheapRng::new();
+    for i in 1..slice.len() {
+        if rng.next_f64() < disorder {
+            slice.swap(i - 1, i)
+        }
+    }
+}
+
+#[test]
+fn quicksort_partial_disorder_levels() {
+    fn inversions(a: &[i32]) -> usize {
+        let mut count = 0;
+        for i in 0..a.len() {
+            for j in i + 1..a.len() {
+                if a[i] > a[j] {
+                    count += 1
+                }
+            }
+        }
+        count
+    }
+
+    let scrambled: Vec<i32> = (0..200).rev().collect();
+
+    // Zero disorder is just a sort.
+    let mut a = scrambled.clone();
+    quicksort_partial_disorder(&mut a, 0.0);
+    assert_eq!(inversions(&a), 0);
+
+    // Full disorder swaps every adjacent pair and so must
+    // produce inversions; the contents survive either way.
+    let mut b = scrambled.clone();
+    quicksort_partial_disorder(&mut b, 1.0);
+    assert!(inversions(&b) > 0);
+    let mut b_sorted = b.clone();
+    quicksort(&mut b_sorted);
+    assert_eq!(a, b_sorted)
+}
+
+/// Sorts `new_data` with `quicksort()`, then merges it
+/// with the already-sorted `sorted_existing` into one
+/// combined sorted `Vec`. This models committing a batch
+/// of new rows into a sorted table: sort the batch, merge
+/// with the base. Either side may be empty. Elements are
+/// cloned into the result; `new_data` is left sorted in
+/// place as a side effect.
+///
+/// # Examples
+///
+/// ```
+/// let mut batch = [5, 1, 3];
+/// let base = [2, 4, 6];
+/// let merged = quicksort::quicksort_then_merge_index(&mut batch, &base);
+/// assert_eq!(merged, [1, 2, 3, 4, 5, 6]);
+/// ```
+#[cfg(feature = "std")]
+pub fn quicksort_then_merge_index<T: Ord + Clone>(
+    new_data: &mut [T],
+    sorted_existing: &[T],
+) -> Vec<T> {
+    quicksort(new_data);
+
+    // Standard two-finger merge. Ties go to the existing
+    // side, so previously committed rows stay ahead of new
+    // ones with equal keys.
+    let mut merged = Vec::with_capacity(new_data.len() + sorted_existing.len());
+    let mut i = 0;
+    let mut j = 0;
+    while i < sorted_existing.len() && j < new_data.len() {
+        if sorted_existing[i] <= new_data[j] {
+            merged.push(sorted_existing[i].clone());
+            i += 1
+        } else {
+            merged.push(new_data[j].clone());
+            j += 1
+        }
+    }
+    merged.extend_from_slice(&sorted_existing[i ..]);
+    merged.extend_from_slice(&new_data[j ..]);
+    merged
+}
+
+#[test]
+fn quicksort_then_merge_index_batches() {
+    let base = [1, 3, 5, 7, 9, 11, 13, 15];
+    let mut batch = [10, 2, 14, 6];
+    let merged = quicksort_then_merge_index(&mut batch, &base);
+    assert_eq!(merged, [1, 2, 3, 5, 6, 7, 9, 10, 11, 13, 14, 15]);
+
+    // Empty on either side.
+    let mut empty: [i32; 0] = [];
+    assert_eq!(quicksort_then_merge_index(&mut empty, &base), base);
+    let mut batch = [4, 2];
+    assert_eq!(quicksort_then_merge_index(&mut batch, &[]), [2, 4])
+}
+
+// Subranges at most this long are finished by the
+// sequential `quicksort()` in `par_quicksort` rather than
+// split into further `rayon::join` tasks.
+#[cfg(feature = "rayon")]
+#[cfg(feature = "std")]
+const PAR_SEQUENTIAL_CUTOFF: usize = 2048;
+
+/// Sorts the slice in parallel: one `partition()` call
+/// splits it, then the two sides are handed to
+/// `rayon::join()` to sort concurrently, recursively.
+/// `split_at_mut()` around the pivot gives each task an
+/// exclusive borrow of its own half, so no synchronization
+/// beyond the join is needed, and the result is exactly
+/// what the sequential `quicksort()` produces. Subranges
+/// of 2048 elements or fewer are finished sequentially so
+/// tiny pieces don't pay task overhead; use
+/// `par_quicksort_with_cutoff()` to tune that threshold.
+#[cfg(feature = "rayon")]
+#[cfg(feature = "std")]
+pub fn par_quicksort<T: Ord + Send>(slice: &mut [T]) {
+    par_quicksort_with_cutoff(slice, PAR_SEQUENTIAL_CUTOFF)
+}
+
+#[cfg(feature = "rayon")]
+#[test]
+fn par_quicksort_matches_sequential() {
+    use rand::Rng;
+    let mut a = Vec::with_capacity(20_000);
+    for _ in 0..20_000 {
+        a.push(rand::thread_rng().gen_range(-5000i64, 5000))
+    }
+    let mut expected = a.clone();
+    quicksort(&mut expected);
+    par_quicksort(&mut a);
+    assert_eq!(a, expected)
+}
+
+/// Sorts the slice in parallel, recursing on the two sides
+/// of each partition with `rayon::join()`, but switching
+/// to the sequential `quicksort()` once a subrange is no
+/// longer than `sequential_cutoff` elements. Spawning
+/// tasks for tiny subarrays costs more than it saves, so
+/// tune the cutoff to your element size and core count; a
+/// few thousand elements (say 1024–8192) is a sensible
+/// default for cheap-to-compare types. A cutoff of zero is
+/// treated as one.
+#[cfg(feature = "rayon")]
+#[cfg(feature = "std")]
+pub fn par_quicksort_with_cutoff<T: Ord + Send>(
+    slice: &mut [T],
+    sequential_cutoff: usize,
+) {
+    if slice.len() <= sequential_cutoff.max(1) {
+        // Below the cutoff the task overhead isn't worth
+        // it: finish sequentially.
+        quicksort(slice);
+        return
+    }
+
+    let pivot_index = partition(slice).unwrap();
+    let (low, high) = slice.split_at_mut(pivot_index);
+    rayon::join(
+        || par_quicksort_with_cutoff(low, sequential_cutoff),
+        || par_quicksort_with_cutoff(&mut high[1 ..], sequential_cutoff),
+    );
+}
+
+#[cfg(feature = "rayon")]
+#[test]
+fn par_quicksort_with_cutoff_extremes() {
+    use rand::Rng;
+    let mut a = Vec::with_capacity(2000);
+    for _ in 0..2000 {
+        a.push(rand::thread_rng().gen_range(-500, 500))
+    }
+    let mut expected = a.clone();
+    quicksort(&mut expected);
+
+    // Cutoff covering the whole slice: fully sequential.
+    let mut b = a.clone();
+    par_quicksort_with_cutoff(&mut b, a.len());
+    assert_eq!(b, expected);
+
+    // Minimal cutoff: maximally parallel.
+    par_quicksort_with_cutoff(&mut a, 0);
+    assert_eq!(a, expected)
+}
+
+// Subranges at most this long are finished sequentially
+// by `par_quicksort_deque` rather than spawned as tasks.
+#[cfg(feature = "rayon")]
+#[cfg(feature = "std")]
+const PAR_DEQUE_CUTOFF: usize = 1024;
+
+/// Sorts the slice in parallel like
+/// `par_quicksort_with_cutoff()`, but instead of blocking
+/// in `rayon::join()` it pushes one side of each partition
+/// onto the pool's work-stealing deques with
+/// `rayon::Scope::spawn()` and loops on the other. Each
+/// worker pushes spawned subranges onto its own deque and
+/// idle workers steal from the far end, which balances
+/// load better than fork-join when partitions are highly
+/// unbalanced. The current worker keeps the smaller side
+/// and spawns the larger, so stolen tasks carry the most
+/// work.
+///
+/// No `unsafe` is needed to share the slice: each
+/// `split_at_mut()` hands the spawned task an exclusive
+/// borrow of a disjoint subrange, and the scope keeps all
+/// tasks from outliving the borrow.
+#[cfg(feature = "rayon")]
+#[cfg(feature = "std")]
+pub fn par_quicksort_deque<T: Ord + Send>(slice: &mut [T]) {
+    rayon::scope(|scope| deque_sort(scope, slice));
+}
+
+// One deque task: loop on the smaller side of each
+// partition, spawning the larger side for idle workers to
+// steal.
+#[cfg(feature = "rayon")]
+#[cfg(feature = "std")]
+fn deque_sort<'a, T: Ord + Send>(
+    scope: &rayon::Scope<'a>,
+    mut slice: &'a mut [T],
+) {
+    while slice.len() > PAR_DEQUE_CUTOFF {
+        let pivot_index = partition(slice).unwrap();
+        let (low, high) = slice.split_at_mut(pivot_index);
+        let high = &mut high[1 ..];
+        let (smaller, larger) =
+            if low.len() <= high.len() { (low, high) } else { (high, low) };
+        scope.spawn(move |scope| deque_sort(scope, larger));
+        slice = smaller
+    }
+    quicksort(slice)
+}
+
+#[cfg(feature = "rayon")]
+#[test]
+fn par_quicksort_deque_unbalanced() {
+    // Sorted runs drive the Hoare partition to its most
+    // unbalanced splits, so nearly every task spawns a
+    // stealable remainder.
+    let mut a: Vec<u32> = (0..10_000).collect();
+    a.extend((0..10_000).rev());
+    let mut expected = a.clone();
+    quicksort(&mut expected);
+    par_quicksort_deque(&mut a);
+    assert_eq!(a, expected)
+}
+
+/// Sorts the slice in place and returns one half-open
+/// `Range` per distinct value, in ascending value order,
+/// giving the span of positions holding that value. This
+/// lets callers iterate over groups of equal elements
+/// without re-scanning for the boundaries. Run detection
+/// is a single pass over the sorted data.
+///
+/// # Examples
+///
+/// ```
+/// let mut a = [2, 1, 2, 2];
+/// let groups = quicksort::quicksort_group_ranges(&mut a);
+/// assert_eq!(groups, [0..1, 1..4]);
+/// ```
+#[cfg(feature = "std")]
+pub fn quicksort_group_ranges<T: Ord>(
+    slice: &mut [T],
+) -> Vec<std::ops::Range<usize>> {
+    quicksort(slice);
+
+    let mut ranges: Vec<std::ops::Range<usize>> = Vec::new();
+    for i in 0..slice.len() {
+        if i == 0 || slice[i - 1] != slice[i] {
+            // New distinct value starts here.
+            ranges.push(i .. i + 1)
+        } else {
+            // Same value: extend the current span.
+            let nranges = ranges.len();
+            ranges[nranges - 1].end = i + 1
+        }
+    }
+    ranges
+}
+
+#[test]
+fn quicksort_group_ranges_spans() {
+    let mut a = ['c', 'a', 'b', 'a', 'c'];
+    let groups = quicksort_group_ranges(&mut a);
+    assert_eq!(a, ['a', 'a', 'b', 'c', 'c']);
+    assert_eq!(groups, [0..2, 2..3, 3..5]);
+
+    // Each range really does hold a single value.
+    for range in groups {
+        let value = a[range.start];
+        for i in range {
+            assert_eq!(a[i], value)
+        }
+    }
+}
+
+/// Sorts the `u64` values ascending by their Hamming
+/// distance to `reference` — the popcount of the XOR of
+/// the two words. Handy for ordering hash fingerprints by
+/// similarity: nearest first. Values at equal distance may
+/// land in any order.
+///
+/// # Examples
+///
+/// ```
+/// let mut a = [0b1111, 0b0000, 0b0011];
+/// quicksort::quicksort_by_hamming(&mut a, 0b0000);
+/// assert_eq!(a, [0b0000, 0b0011, 0b1111]);
+/// ```
+#[cfg(feature = "std")]
+pub fn quicksort_by_hamming(slice: &mut [u64], reference: u64) {
+    quicksort_by_compare(slice, &mut |a: &u64, b: &u64| {
+        (a ^ reference).count_ones().cmp(&(b ^ reference).count_ones())
+    })
+}
+
+#[test]
+fn quicksort_by_hamming_distances() {
+    let reference = 0xffff_0000u64;
+    // Values constructed at distances 0, 2, 5, 9, 16 from
+    // the reference.
+    let d0 = reference;
+    let d2 = reference ^ 0b11;
+    let d5 = reference ^ 0b1_1111;
+    let d9 = reference ^ 0x1ff;
+    let d16 = reference ^ 0xffff;
+    let mut a = [d9, d0, d16, d2, d5];
+    quicksort_by_hamming(&mut a, reference);
+    assert_eq!(a, [d0, d2, d5, d9, d16])
+}
+
+/// Collects an iterator, sorts it with `quicksort()`, and
+/// drops adjacent duplicates, returning the distinct
+/// values in ascending order. The whole pipeline is the
+/// usual expected `O(n log n)`: collection and dedup are
+/// linear, the sort dominates. Duplicates are held only
+/// until the final dedup pass.
+///
+/// # Examples
+///
+/// ```
+/// let v = quicksort::sorted_unique(vec![3, 1, 3, 2, 1, 1]);
+/// assert_eq!(v, [1, 2, 3]);
+/// ```
+#[cfg(feature = "std")]
+pub fn sorted_unique<T: Ord, I: IntoIterator<Item = T>>(iter: I) -> Vec<T> {
+    let mut values: Vec<T> = iter.into_iter().collect();
+    quicksort(&mut values);
+    // Equal values are now adjacent, so `dedup()` removes
+    // all duplicates.
+    values.dedup();
+    values
+}
+
+#[test]
+fn sorted_unique_many_duplicates() {
+    let v = sorted_unique((0..1000).map(|i| i % 7));
+    assert_eq!(v, [0, 1, 2, 3, 4, 5, 6]);
+
+    let empty: Vec<i32> = sorted_unique(std::iter::empty());
+    assert!(empty.is_empty())
+}
+
+// Linear scan for sortedness, used by the adaptive entry
+// points to short-circuit already-ordered subranges.
+#[cfg(feature = "std")]
+fn run_is_sorted<T: Ord>(slice: &[T]) -> bool {
+    for i in 1..slice.len() {
+        if slice[i - 1] > slice[i] {
+            return false
+        }
+    }
+    true
+}
+
+/// Sorts like `quicksort()`, but at each recursive call on
+/// a subarray of at least `scan_threshold` elements first
+/// makes a linear already-sorted check and skips the
+/// partition when it passes. The scan costs `O(n)` per
+/// call, which pays off handsomely on nearly sorted data
+/// but is pure overhead on the many tiny subarrays near
+/// the recursion leaves — the threshold says where to stop
+/// bothering. Around 16 is a reasonable default; a
+/// threshold of 0 scans everywhere, a threshold beyond the
+/// slice length never scans.
+///
+/// On input that is already sorted (and at least
+/// `scan_threshold` long), the top-level scan settles
+/// everything and the sort makes exactly the optimal
+/// `n - 1` comparisons.
+///
+/// # Examples
+///
+/// ```
+/// let mut a = [5, 1, 0, 4, 3, 2];
+/// quicksort::quicksort_adaptive_tuned(&mut a, 16);
+/// assert_eq!(a, [0, 1, 2, 3, 4, 5]);
+/// ```
+#[cfg(feature = "std")]
+pub fn quicksort_adaptive_tuned<T: Ord>(slice: &mut [T], scan_threshold: usize) {
+    let nslice = slice.len();
+    if nslice <= 1 {
+        return;  // Nothing to sort.
+    }
+
+    // Big enough to be worth a look: bail out if already
+    // in order.
+    if nslice >= scan_threshold && run_is_sorted(slice) {
+        return
+    }
+
+    let pivot_index = partition(slice).unwrap();
+    quicksort_adaptive_tuned(&mut slice[.. pivot_index], scan_threshold);
+    quicksort_adaptive_tuned(&mut slice[pivot_index + 1 ..], scan_threshold);
+}
+
+#[test]
+fn quicksort_adaptive_tuned_thresholds() {
+    use rand::Rng;
+    let mut a = Vec::with_capacity(300);
+    for _ in 0..300 {
+        a.push(rand::thread_rng().gen_range(-100, 100))
+    }
+    let mut expected = a.clone();
+    quicksort(&mut expected);
+
+    // Always scan.
+    let mut b = a.clone();
+    quicksort_adaptive_tuned(&mut b, 0);
+    assert_eq!(b, expected);
+
+    // Never scan.
+    quicksort_adaptive_tuned(&mut a, usize::MAX);
+    assert_eq!(a, expected)
+}
+
+/// Sorts the slice in place ascending, then returns an
+/// iterator walking the sorted data backward, yielding
+/// elements from largest to smallest. No reversed copy is
+/// allocated; the iterator just borrows the slice.
+///
+/// # Examples
+///
+/// ```
+/// let mut a = [2, 5, 1, 4];
+/// let descending: Vec<i32> =
+///     quicksort::sorted_rev_iter(&mut a).cloned().collect();
+/// assert_eq!(descending, [5, 4, 2, 1]);
+/// ```
+#[cfg(feature = "std")]
+pub fn sorted_rev_iter<T: Ord>(slice: &mut [T]) -> impl Iterator<Item = &T> {
+    quicksort(slice);
+    slice.iter().rev()
+}
+
+/// Sorts `(start, end)` interval tuples by start
+/// ascending, breaking ties by end ascending — the
+/// standard preprocessing for interval-merge and
+/// scheduling algorithms. Tuples already order
+/// lexicographically, so this is `quicksort()` with the
+/// tiebreak made explicit in the name.
+///
+/// # Examples
+///
+/// ```
+/// let mut a = [(3, 4), (1, 5), (1, 2)];
+/// quicksort::quicksort_intervals(&mut a);
+/// assert_eq!(a, [(1, 2), (1, 5), (3, 4)]);
+/// ```
+#[cfg(feature = "std")]
+pub fn quicksort_intervals<T: Ord + Copy>(slice: &mut [(T, T)]) {
+    quicksort(slice)
+}
+
+#[test]
+fn quicksort_intervals_overlapping() {
+    let mut a = [
+        (5, 9), (1, 4), (5, 6), (0, 10), (1, 2), (5, 7),
+    ];
+    quicksort_intervals(&mut a);
+    assert_eq!(a, [
+        (0, 10), (1, 2), (1, 4), (5, 6), (5, 7), (5, 9),
+    ])
+}
+
+/// Moves the `k` largest elements under `compare` into the
+/// last `k` positions of the slice, using quickselect
+/// rather than a full sort so an expensive comparator is
+/// called `O(n)` expected times instead of `O(n log n)`.
+/// Only the boundary is settled: everything in the final
+/// `k` slots is `>=` everything before them, but neither
+/// side is internally ordered. `k >= slice.len()` is a
+/// no-op (everything is trivially in the top `k`).
+///
+/// # Examples
+///
+/// ```
+/// let mut a = [4, 9, 1, 7, 3];
+/// quicksort::top_k_by(&mut a, 2, |x, y| x.cmp(y));
+/// let mut top: Vec<i32> = a[3..].to_vec();
+/// top.sort();
+/// assert_eq!(top, [7, 9]);
+/// ```
+#[cfg(feature = "std")]
+pub fn top_k_by<T, F: FnMut(&T, &T) -> Ordering>(
+    slice: &mut [T],
+    k: usize,
+    mut compare: F,
+) {
+    let nslice = slice.len();
+    if k == 0 || k >= nslice {
+        return
+    }
+
+    // Quickselect for the element that belongs at the
+    // boundary position, narrowing the active range around
+    // it.
+    let target = nslice - k;
+    let mut lo = 0;
+    let mut hi = nslice;
+    loop {
+        let nrange = hi - lo;
+        let pivot_index = lo + partition_around_by(
+            &mut slice[lo .. hi],
+            nrange / 2,
+            &mut compare,
+        );
+        if pivot_index == target {
+            return
+        }
+        if pivot_index < target {
+            lo = pivot_index + 1
+        } else {
+            hi = pivot_index
+        }
+    }
+}
+
+#[test]
+fn top_k_by_field() {
+    struct Player {
+        name: &'static str,
+        score: u32,
+    }
+    let player = |name, score| Player { name, score };
+
+    let mut roster = vec![
+        player("ann", 17), player("bob", 42), player("cam", 5),
+        player("deb", 99), player("eve", 23), player("fay", 42),
+        player("gil", 8),
+    ];
+    let nroster = roster.len();
+    top_k_by(&mut roster, 3, |a, b| a.score.cmp(&b.score));
+
+    // The top three by score, as a set.
+    let mut top: Vec<&'static str> = roster[nroster - 3 ..]
+        .iter()
+        .map(|p| p.name)
+        .collect();
+    quicksort(&mut top);
+    assert_eq!(top, ["bob", "deb", "fay"])
+}
+
+/// Sorts the slice iteratively using a fixed-size on-stack
+/// array of pending ranges instead of recursion or a heap
+/// allocation, which suits `no_std` and allocation-averse
+/// callers. After each partition the larger side is
+/// deferred onto the range stack and work continues on the
+/// smaller side, so the live range shrinks by at least
+/// half per deferral and the stack never holds more than
+/// `log2(n)` entries: the 64-slot stack covers any slice
+/// addressable on a 64-bit machine, and cannot overflow.
+///
+/// # Examples
+///
+/// ```
+/// let mut a = [5, 1, 0, 4, 3, 2];
+/// quicksort::quicksort_fixed_stack(&mut a);
+/// assert_eq!(a, [0, 1, 2, 3, 4, 5]);
+/// ```
+#[cfg(feature = "std")]
+pub fn quicksort_fixed_stack<T: Ord>(slice: &mut [T]) {
+    // Pending half-open ranges, most recently deferred on
+    // top.
+    let mut stack = [(0usize, 0usize); 64];
+    let mut top = 0;
+
+    let mut lo = 0;
+    let mut hi = slice.len();
+    loop {
+        while hi - lo > 1 {
+            let pivot_index = lo + partition(&mut slice[lo .. hi]).unwrap();
+
+            // Defer the larger side, keep the smaller:
+            // that's what bounds the stack.
+            if pivot_index - lo < hi - pivot_index {
+                stack[top] = (pivot_index + 1, hi);
+                top += 1;
+                hi = pivot_index
+            } else {
+                stack[top] = (lo, pivot_index);
+                top += 1;
+                lo = pivot_index + 1
+            }
+        }
+
+        // Current range is done; resume a deferred one.
+        if top == 0 {
+            return
+        }
+        top -= 1;
+        let (next_lo, next_hi) = stack[top];
+        lo = next_lo;
+        hi = next_hi
+    }
+}
+
+#[test]
+fn quicksort_fixed_stack_large() {
+    use rand::Rng;
+    let mut a = Vec::with_capacity(10_000);
+    for _ in 0..10_000 {
+        a.push(rand::thread_rng().gen_range(-100_000, 100_000))
+    }
+    let mut expected = a.clone();
+    quicksort(&mut expected);
+    quicksort_fixed_stack(&mut a);
+    assert_eq!(a, expected);
+
+    // Degenerate sizes shouldn't touch the stack at all.
+    let mut empty: [u8; 0] = [];
+    quicksort_fixed_stack(&mut empty);
+    let mut one = [1];
+    quicksort_fixed_stack(&mut one);
+    assert_eq!(one, [1])
+}
+
+/// Sorts strings by ASCII-case-folded order: ASCII letters
+/// are lowercased for comparison only, and the stored
+/// strings are left untouched. This is the everyday "sort
+/// names ignoring case" operation. Only ASCII is folded —
+/// no Unicode case folding or locale collation — so, e.g.,
+/// `'É'` and `'é'` still compare as distinct bytes.
+///
+/// # Examples
+///
+/// ```
+/// let mut a = vec![
+///     "banana".to_string(),
+///     "Apple".to_string(),
+///     "cherry".to_string(),
+/// ];
+/// quicksort::quicksort_strings_ci(&mut a);
+/// assert_eq!(a, ["Apple", "banana", "cherry"]);
+/// ```
+#[cfg(feature = "std")]
+pub fn quicksort_strings_ci(slice: &mut [String]) {
+    quicksort_by_compare(slice, &mut |a: &String, b: &String| {
+        a.bytes()
+            .map(|b| b.to_ascii_lowercase())
+            .cmp(b.bytes().map(|b| b.to_ascii_lowercase()))
+    })
+}
+
+#[test]
+fn quicksort_strings_ci_mixed_case() {
+    let mut a: Vec<String> = [
+        "banana", "Apple", "cherry", "BANDANA", "apricot",
+    ].iter().map(|s| s.to_string()).collect();
+    quicksort_strings_ci(&mut a);
+    assert_eq!(a, ["Apple", "apricot", "banana", "BANDANA", "cherry"])
+}
+
+/// Sorts the slice and returns how many elements ended up
+/// at the same index they started at — the fixed points of
+/// the sort permutation. A high count is a cheap signal
+/// that the data was nearly sorted to begin with. Original
+/// positions are tracked by tagging a working copy, which
+/// is why `Clone` is required.
+///
+/// # Examples
+///
+/// ```
+/// let mut a = [1, 3, 2, 4];
+/// let fixed = quicksort::quicksort_fixed_points(&mut a);
+/// assert_eq!(a, [1, 2, 3, 4]);
+/// assert_eq!(fixed, 2);  // 1 and 4 didn't move.
+/// ```
+#[cfg(feature = "std")]
+pub fn quicksort_fixed_points<T: Ord + Clone>(slice: &mut [T]) -> usize {
+    // Tag each element with where it started, and sort the
+    // tagged copy by value alone.
+    let mut tagged: Vec<(T, usize)> = slice
+        .iter()
+        .cloned()
+        .enumerate()
+        .map(|(i, v)| (v, i))
+        .collect();
+    quicksort_by_compare(&mut tagged, &mut |a: &(T, usize), b: &(T, usize)| {
+        a.0.cmp(&b.0)
+    });
+
+    // Write the sorted values back and count the
+    // stay-at-homes.
+    let mut fixed = 0;
+    for (i, (v, start)) in tagged.into_iter().enumerate() {
+        if start == i {
+            fixed += 1
+        }
+        slice[i] = v
+    }
+    fixed
+}
+
+#[test]
+fn quicksort_fixed_points_extremes() {
+    // Already sorted: everything is a fixed point.
+    let mut a = [1, 2, 3, 4, 5, 6];
+    assert_eq!(quicksort_fixed_points(&mut a), 6);
+    assert_eq!(a, [1, 2, 3, 4, 5, 6]);
+
+    // Reversed, even length: nothing stays put.
+    let mut b = [6, 5, 4, 3, 2, 1];
+    assert_eq!(quicksort_fixed_points(&mut b), 0);
+    assert_eq!(b, [1, 2, 3, 4, 5, 6])
+}
+
+/// Reorders the vector so that the most frequently
+/// occurring values come first. Equal values stay grouped
+/// together, and values with the same occurrence count are
+/// ordered ascending by value. Frequencies are counted in
+/// one hash-map pass, then the sort keys on (descending
+/// count, ascending value).
+///
+/// # Examples
+///
+/// ```
+/// let mut v = vec![9, 7, 9, 8, 9, 7];
+/// quicksort::quicksort_by_frequency(&mut v);
+/// assert_eq!(v, [9, 9, 9, 7, 7, 8]);
+/// ```
+#[allow(clippy::ptr_arg)]
+#[cfg(feature = "std")]
+pub fn quicksort_by_frequency<T: Ord + Clone + std::hash::Hash>(
+    vec: &mut Vec<T>,
+) {
+    // Count occurrences.
+    let mut counts: std::collections::HashMap<T, usize> =
+        std::collections::HashMap::new();
+    for v in vec.iter() {
+        *counts.entry(v.clone()).or_insert(0) += 1
+    }
+
+    // Most common first; break count ties by value so the
+    // result is deterministic and equal values stay
+    // adjacent.
+    quicksort_by_compare(vec, &mut |a: &T, b: &T| {
+        counts[b].cmp(&counts[a]).then_with(|| a.cmp(b))
+    })
+}
+
+#[test]
+fn quicksort_by_frequency_grouped() {
+    let mut v = vec!['a', 'b', 'a', 'c', 'a', 'b'];
+    quicksort_by_frequency(&mut v);
+    assert_eq!(v, ['a', 'a', 'a', 'b', 'b', 'c']);
+
+    // Count ties ('x' and 'y' both appear twice) fall back
+    // to value order.
+    let mut v = vec!['y', 'x', 'z', 'y', 'x', 'z', 'z'];
+    quicksort_by_frequency(&mut v);
+    assert_eq!(v, ['z', 'z', 'z', 'x', 'x', 'y', 'y'])
+}
+
+/// Error from `quicksort_with_constraints()`: the
+/// precedence constraints cannot all be honored, either
+/// because one contradicts the natural order outright or
+/// because constraints among equal elements form a cycle.
+#[derive(Debug, PartialEq, Eq)]
+#[cfg(feature = "std")]
+pub struct CycleError;
+
+/// Sorts the slice by its natural order while honoring
+/// extra precedence constraints: each `(i, j)` pair in
+/// `must_precede` demands that the element *originally* at
+/// index `i` end up before the element originally at index
+/// `j`. A constraint between unequal values is either
+/// already implied by the natural order (fine) or
+/// contradicts it (`Err(CycleError)` — we never misorder
+/// values to satisfy a constraint). Constraints between
+/// equal values steer how the tie is broken; if those
+/// constraints are themselves cyclic, that is also a
+/// `CycleError`. Ties not touched by any constraint break
+/// by original position.
+#[cfg(feature = "std")]
+pub fn quicksort_with_constraints<T: Ord>(
+    slice: &mut [T],
+    must_precede: &[(usize, usize)],
+) -> Result<(), CycleError> {
+    let nslice = slice.len();
+
+    // Natural order of original indices, ties by position.
+    let mut order: Vec<usize> = (0..nslice).collect();
+    quicksort_by_compare(&mut order, &mut |a: &usize, b: &usize| {
+        slice[*a].cmp(&slice[*b]).then_with(|| a.cmp(b))
+    });
+
+    // Vet the constraints, keeping the ones that actually
+    // constrain (ties).
+    let mut tie_edges: Vec<(usize, usize)> = Vec::new();
+    for &(i, j) in must_precede {
+        match slice[i].cmp(&slice[j]) {
+            Ordering::Greater => return Err(CycleError),
+            Ordering::Equal => tie_edges.push((i, j)),
+            Ordering::Less => (),  // Implied by the sort.
+        }
+    }
+
+    // Re-break ties inside each run of equal values with a
+    // topological order over the constrained pairs,
+    // preferring smaller original indices.
+    let mut run_start = 0;
+    while run_start < nslice {
+        let mut run_end = run_start + 1;
+        while run_end < nslice
+            && slice[order[run_start]] == slice[order[run_end]] {
+            run_end += 1
+        }
+
+        let run = &mut order[run_start .. run_end];
+        if run.len() > 1 {
+            // Kahn's algorithm over this run's members.
+            let members: Vec<usize> = run.to_vec();
+            let indegree = |k: usize, placed: &[bool]| {
+                tie_edges
+                    .iter()
+                    .filter(|&&(i, j)| {
+                        // Equal values share a run, so a
+                        // tie edge's endpoints are always
+                        // both members.
+                        j == k && !placed[members
+                            .iter()
+                            .position(|&m| m == i)
+                            .unwrap()]
+                    })
+                    .count()
+            };
+            let mut placed = vec![false; members.len()];
+            for slot in run.iter_mut() {
+                // Smallest unplaced member with no pending
+                // predecessor.
+                let mut choice = None;
+                for (p, &m) in members.iter().enumerate() {
+                    if !placed[p]
+                        && indegree(m, &placed) == 0
+                        && choice.is_none_or(|(_, best)| m < best) {
+                        choice = Some((p, m))
+                    }
+                }
+                match choice {
+                    Some((p, m)) => {
+                        placed[p] = true;
+                        *slot = m
+                    },
+                    // Everyone left has a predecessor:
+                    // that's a cycle.
+                    None => return Err(CycleError),
+                }
+            }
+        }
+        run_start = run_end
+    }
+
+    // `order[k]` is the original index that belongs at
+    // position `k`; invert it to per-element destinations
+    // and apply with cycle-following swaps.
+    let mut dest = vec![0; nslice];
+    for (k, &src) in order.iter().enumerate() {
+        dest[src] = k
+    }
+    for i in 0..nslice {
+        while dest[i] != i {
+            let j = dest[i];
+            slice.swap(i, j);
+            dest.swap(i, j)
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn quicksort_with_constraints_ties_and_cycles() {
+    // Equal keys whose marker field is invisible to `Ord`,
+    // so only a constraint can dictate their final order.
+    struct Kv(u32, char);
+
+    impl PartialEq for Kv {
+        fn eq(&self, other: &Kv) -> bool {
+            self.0 == other.0
+        }
+    }
+
+    impl Eq for Kv {}
+
+    impl PartialOrd for Kv {
+        fn partial_cmp(&self, other: &Kv) -> Option<Ordering> {
+            Some(self.cmp(other))
+        }
+    }
+
+    impl Ord for Kv {
+        fn cmp(&self, other: &Kv) -> Ordering {
+            self.0.cmp(&other.0)
+        }
+    }
+
+    // Natural stable order would give (2,'b') before
+    // (2,'c'); the constraint flips that tie.
+    let mut a = [Kv(2, 'b'), Kv(1, 'a'), Kv(2, 'c'), Kv(3, 'd')];
+    assert_eq!(quicksort_with_constraints(&mut a, &[(2, 0)]), Ok(()));
+    let markers: Vec<char> = a.iter().map(|kv| kv.1).collect();
+    assert_eq!(markers, ['a', 'c', 'b', 'd']);
+
+    // A constraint against the natural order is refused.
+    let mut b = [Kv(2, 'b'), Kv(1, 'a')];
+    assert_eq!(
+        quicksort_with_constraints(&mut b, &[(0, 1)]),
+        Err(CycleError)
+    );
+
+    // So is a cycle among equal elements.
+    let mut c = [Kv(5, 'x'), Kv(5, 'y')];
+    assert_eq!(
+        quicksort_with_constraints(&mut c, &[(0, 1), (1, 0)]),
+        Err(CycleError)
+    )
+}
+
+// Recursive worker for `quicksort_autotune()`. The sample
+// size is shared down the recursion, so imbalance observed
+// early makes every later partition sample harder.
+#[cfg(feature = "std")]
+fn autotune_sort<T: Ord>(slice: &mut [T], sample_size: &mut usize) {
+    let nslice = slice.len();
+    if nslice <= 1 {
+        return;  // Nothing to sort.
+    }
+
+    // Pivot on the median of an equally-spaced sample.
+    let nsample = (*sample_size).min(nslice);
+    let mut sample: Vec<usize> = (0..nsample)
+        .map(|k| k * (nslice - 1) / (nsample - 1).max(1))
+        .collect();
+    // The sample is tiny, so an insertion sort by value is
+    // plenty.
+    for i in 1..sample.len() {
+        let mut j = i;
+        while j > 0 && slice[sample[j - 1]] > slice[sample[j]] {
+            sample.swap(j - 1, j);
+            j -= 1
+        }
+    }
+    let choice = sample[sample.len() / 2];
+    let pivot_index = partition_around(slice, choice);
+
+    // Balance feedback: a split worse than 1:3 suggests
+    // the sample was too easy to fool, so double it
+    // (capped — past a few dozen the median is already
+    // hard to skew).
+    let smaller = pivot_index.min(nslice - 1 - pivot_index);
+    if smaller * 4 < nslice && *sample_size < 33 {
+        *sample_size = *sample_size * 2 + 1
+    }
+
+    autotune_sort(&mut slice[.. pivot_index], sample_size);
+    autotune_sort(&mut slice[pivot_index + 1 ..], sample_size);
+}
+
+/// Sorts the slice with a self-tuning sampled pivot: each
+/// partition pivots on the median of a small equally
+/// spaced sample (initially 3 elements), and whenever a
+/// partition comes out worse than a 1:3 split the sample
+/// size is doubled (up to 33) for the rest of the sort.
+/// Well-behaved data pays only the cheap 3-element median;
+/// adversarial or heavily skewed data quickly escalates to
+/// a sample that is hard to fool, heading off the
+/// quadratic worst case at run time rather than by fixed
+/// policy.
+///
+/// # Examples
+///
+/// ```
+/// let mut a = [5, 1, 0, 4, 3, 2];
+/// quicksort::quicksort_autotune(&mut a);
+/// assert_eq!(a, [0, 1, 2, 3, 4, 5]);
+/// ```
+#[cfg(feature = "std")]
+pub fn quicksort_autotune<T: Ord>(slice: &mut [T]) {
+    let mut sample_size = 3;
+    autotune_sort(slice, &mut sample_size)
+}
+
+// A test-only element that tallies every comparison made
+// on it through a shared counter, so tests can assert
+// comparison counts for the various strategies.
+#[cfg(test)]
+struct CountedCmp<'a>(i64, &'a std::cell::Cell<u64>);
+
+#[cfg(test)]
+impl<'a> PartialEq for CountedCmp<'a> {
+    fn eq(&self, other: &CountedCmp<'a>) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+#[cfg(test)]
+impl<'a> Eq for CountedCmp<'a> {}
+
+#[cfg(test)]
+impl<'a> PartialOrd for CountedCmp<'a> {
+    fn partial_cmp(&self, other: &CountedCmp<'a>) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+#[cfg(test)]
+impl<'a> Ord for CountedCmp<'a> {
+    fn cmp(&self, other: &CountedCmp<'a>) -> Ordering {
+        self.1.set(self.1.get() + 1);
+        self.0.cmp(&other.0)
+    }
+}
+
+#[test]
+fn quicksort_autotune_beats_fixed_pivot() {
+    use std::cell::Cell;
+
+    // Already-sorted input is the classic killer for a
+    // fixed first-element pivot.
+    let count = Cell::new(0);
+    let mut a: Vec<CountedCmp> =
+        (0..300).map(|i| CountedCmp(i, &count)).collect();
+    quicksort_autotune(&mut a);
+    let tuned = count.get();
+
+    count.set(0);
+    let mut b: Vec<CountedCmp> =
+        (0..300).map(|i| CountedCmp(i, &count)).collect();
+    let mut first = std::iter::repeat(0);
+    quicksort_with_pivots(&mut b, &mut first);
+    let fixed = count.get();
+
+    assert!(tuned < fixed,
+            "autotune used {} comparisons, fixed pivot {}",
+            tuned, fixed);
+    for i in 1..300 {
+        assert!(a[i - 1].0 <= a[i].0)
+    }
+}
+
+/// Sorts the slice and returns the `k` elements nearest to
+/// `target`. With only `Ord` available there is no numeric
+/// distance, so "nearest" means nearest in rank: elements
+/// are taken outward from `target`'s insertion point in
+/// the sorted order, fewest intervening elements first.
+/// Elements `>= target` at a given rank distance count as
+/// slightly closer than elements below at the same
+/// distance would suggest — exact matches come first — and
+/// when the two sides are equidistant in rank the smaller
+/// value wins. Asking for more elements than exist returns
+/// them all.
+///
+/// # Examples
+///
+/// ```
+/// let mut a = [20, 7, 1, 10, 6];
+/// let near = quicksort::k_nearest(&mut a, &7, 3);
+/// assert_eq!(near, [7, 6, 10]);
+/// ```
+#[cfg(feature = "std")]
+pub fn k_nearest<T: Ord + Clone>(slice: &mut [T], target: &T, k: usize) -> Vec<T> {
+    quicksort(slice);
+    let nslice = slice.len();
+
+    // Insertion point: first element at or above the
+    // target.
+    let split = slice
+        .iter()
+        .position(|v| v >= target)
+        .unwrap_or(nslice);
+
+    // Walk outward, closest rank first.
+    let mut left = split;       // Next candidate below is left - 1.
+    let mut right = split;      // Next candidate at or above is right.
+    let mut nearest = Vec::with_capacity(k.min(nslice));
+    while nearest.len() < k && (left > 0 || right < nslice) {
+        // Rank distances of the two frontier candidates.
+        let dl = if left > 0 { split - left + 1 } else { usize::MAX };
+        let dr = if right < nslice { right - split } else { usize::MAX };
+        if dl <= dr {
+            left -= 1;
+            nearest.push(slice[left].clone())
+        } else {
+            nearest.push(slice[right].clone());
+            right += 1
+        }
+    }
+    nearest
+}
+
+#[test]
+fn k_nearest_matches_brute_force() {
+    // Gaps chosen so rank distance and numeric distance
+    // agree.
+    let mut a = [20, 3, 14, 7, 1, 10, 6];
+    let target = 7;
+    let k = 4;
+    let mut near = k_nearest(&mut a, &target, k);
+
+    // Brute force: order by absolute difference.
+    let mut by_diff = a.to_vec();
+    quicksort_by_compare(&mut by_diff, &mut |x: &i32, y: &i32| {
+        (x - target).abs().cmp(&(y - target).abs())
+    });
+    let mut expected = by_diff[.. k].to_vec();
+
+    quicksort(&mut near);
+    quicksort(&mut expected);
+    assert_eq!(near, expected);
+
+    // Oversized k returns everything.
+    assert_eq!(k_nearest(&mut a, &target, 100).len(), a.len())
+}
+
+// Count inversions (pairs in strictly decreasing order) by
+// merge-sorting a vector of indices by their values in
+// `slice`. The data itself is never moved.
+#[cfg(feature = "std")]
+fn count_inversions<T: Ord>(slice: &[T]) -> u64 {
+    fn merge_count<T: Ord>(
+        slice: &[T],
+        indices: &mut Vec<usize>,
+        scratch: &mut Vec<usize>,
+    ) -> u64 {
+        let nindices = indices.len();
+        if nindices <= 1 {
+            return 0
+        }
+        let mid = nindices / 2;
+        let mut right: Vec<usize> = indices.split_off(mid);
+        let mut inversions = merge_count(slice, indices, scratch)
+            + merge_count(slice, &mut right, scratch);
+
+        // Merge back into `indices`, counting pairs that
+        // cross out of order. Ties are not inversions.
+        scratch.clear();
+        let mut i = 0;
+        let mut j = 0;
+        while i < indices.len() && j < right.len() {
+            if slice[indices[i]] <= slice[right[j]] {
+                scratch.push(indices[i]);
+                i += 1
+            } else {
+                // Everything left on the left side beats
+                // this right element.
+                inversions += (indices.len() - i) as u64;
+                scratch.push(right[j]);
+                j += 1
+            }
+        }
+        scratch.extend_from_slice(&indices[i ..]);
+        scratch.extend_from_slice(&right[j ..]);
+        indices.clear();
+        indices.extend_from_slice(scratch);
+        inversions
+    }
+
+    let mut indices: Vec<usize> = (0..slice.len()).collect();
+    let mut scratch = Vec::with_capacity(slice.len());
+    merge_count(slice, &mut indices, &mut scratch)
+}
+
+/// Returns the normalized Kendall tau distance between the
+/// slice's current order and its fully sorted order: the
+/// number of inversions divided by the `n * (n - 1) / 2`
+/// possible pairs, giving `0.0` for sorted input and `1.0`
+/// for strictly reversed input. The inversion count comes
+/// from an `O(n log n)` merge pass over an index
+/// permutation, so the data itself is not moved. Slices
+/// shorter than two elements report `0.0`.
+///
+/// # Examples
+///
+/// ```
+/// assert_eq!(quicksort::kendall_tau(&[1, 2, 3]), 0.0);
+/// assert_eq!(quicksort::kendall_tau(&[3, 2, 1]), 1.0);
+/// ```
+#[cfg(feature = "std")]
+pub fn kendall_tau<T: Ord>(slice: &[T]) -> f64 {
+    let nslice = slice.len() as u64;
+    if nslice < 2 {
+        return 0.0
+    }
+    let npairs = nslice * (nslice - 1) / 2;
+    count_inversions(slice) as f64 / npairs as f64
+}
+
+#[test]
+fn kendall_tau_known_values() {
+    let sorted: Vec<u32> = (0..50).collect();
+    assert_eq!(kendall_tau(&sorted), 0.0);
+
+    let reversed: Vec<u32> = (0..50).rev().collect();
+    assert_eq!(kendall_tau(&reversed), 1.0);
+
+    // One adjacent transposition in 4 elements: exactly 1
+    // of 6 pairs inverted.
+    assert_eq!(kendall_tau(&[0, 2, 1, 3]), 1.0 / 6.0)
+}
+
+/// Sorts the slice, then deals the sorted elements
+/// round-robin into `n` output vectors: element `i` of the
+/// sorted order goes to stream `i % n`. Each stream is
+/// therefore itself sorted, the stream lengths differ by
+/// at most one, and interleaving the streams in deal order
+/// reconstructs the full sorted sequence — balanced,
+/// individually ordered work for `n` parallel consumers.
+///
+/// # Panics
+///
+/// Panics if `n` is zero.
+///
+/// # Examples
+///
+/// ```
+/// let mut a = [4, 1, 3, 2];
+/// let streams = quicksort::quicksort_split_streams(&mut a, 2);
+/// assert_eq!(streams, [vec![1, 3], vec![2, 4]]);
+/// ```
+#[cfg(feature = "std")]
+pub fn quicksort_split_streams<T: Ord + Clone>(
+    slice: &mut [T],
+    n: usize,
+) -> Vec<Vec<T>> {
+    assert!(n > 0, "cannot split into zero streams");
+    quicksort(slice);
+
+    let mut streams = vec![Vec::with_capacity(slice.len() / n + 1); n];
+    for (i, v) in slice.iter().enumerate() {
+        streams[i % n].push(v.clone())
+    }
+    streams
+}
+
+#[test]
+fn quicksort_split_streams_three_ways() {
+    let mut a = [9, 1, 8, 2, 7, 3, 6, 4, 5, 0, 10];
+    let streams = quicksort_split_streams(&mut a, 3);
+    assert_eq!(streams.len(), 3);
+
+    // Each stream is sorted.
+    for stream in &streams {
+        for i in 1..stream.len() {
+            assert!(stream[i - 1] <= stream[i])
+        }
+    }
+
+    // Interleaving reconstructs the sorted whole.
+    let mut rebuilt = Vec::new();
+    for i in 0..a.len() {
+        rebuilt.push(streams[i % 3][i / 3])
+    }
+    assert_eq!(rebuilt, a.to_vec())
+}
+
+/// Sorts the slice by partitioning an index permutation
+/// instead of the data, then applying the permutation with
+/// cycle-following swaps. The expensive `O(n log n)` swap
+/// traffic lands on `usize` indices; the elements
+/// themselves move at most `n - 1` times total. The
+/// default `quicksort()` selects this strategy
+/// automatically for elements over 64 bytes, but it can be
+/// called directly when profiling says data movement is
+/// the bottleneck.
+///
+/// # Examples
+///
+/// ```
+/// let mut a = [5, 1, 0, 4, 3, 2];
+/// quicksort::quicksort_indirect(&mut a);
+/// assert_eq!(a, [0, 1, 2, 3, 4, 5]);
+/// ```
+#[cfg(feature = "std")]
+pub fn quicksort_indirect<T: Ord>(slice: &mut [T]) {
+    let nslice = slice.len();
+
+    // Sort indices by the values they name.
+    let mut order: Vec<usize> = (0..nslice).collect();
+    quicksort_by_compare(&mut order, &mut |a: &usize, b: &usize| {
+        slice[*a].cmp(&slice[*b])
+    });
+
+    // Invert to destinations and permute the data once.
+    let mut dest = vec![0; nslice];
+    for (k, &src) in order.iter().enumerate() {
+        dest[src] = k
+    }
+    for i in 0..nslice {
+        while dest[i] != i {
+            let j = dest[i];
+            slice.swap(i, j);
+            dest.swap(i, j)
+        }
+    }
+}
+
+#[test]
+fn quicksort_auto_indirect() {
+    // A deliberately bulky record: well over the 64-byte
+    // threshold.
+    #[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug)]
+    struct Big {
+        key: i64,
+        payload: [u64; 16],
+    }
+    let big = |key| Big { key, payload: [key as u64; 16] };
+
+    let keys = [5i64, 1, 9, 3, 7, 2, 8, 0, 6, 4];
+    let mut data: Vec<Big> = keys.iter().map(|&k| big(k)).collect();
+    // Routed through the indirect strategy by size.
+    quicksort(&mut data);
+    for (i, v) in data.iter().enumerate() {
+        assert_eq!(v.key, i as i64)
+    }
+
+    // A tiny type takes the direct path and sorts too.
+    let mut small = keys;
+    quicksort(&mut small);
+    assert_eq!(small, [0, 1, 2, 3, 4, 5, 6, 7, 8, 9]);
+
+    // The indirect strategy really does move the data
+    // less: applying a permutation needs at most n - 1
+    // element swaps, while the direct scan's swap sequence
+    // (as recorded by `quicksort_swap_plan`) is much
+    // longer on scrambled input.
+    let data: Vec<Big> = keys.iter().map(|&k| big(k)).collect();
+    let direct_moves = quicksort_swap_plan(&data).len();
+    assert!(direct_moves > data.len() - 1,
+            "direct strategy made only {} moves", direct_moves)
+}
+
+/// Sorts `slice` by keys that live in separate parallel
+/// arrays: element `i` is keyed by `keys[0][i]`, with
+/// `keys[1][i]` breaking ties, and so on down the columns.
+/// The sort shuffles an index permutation while comparing
+/// column values, then permutes `slice` once at the end,
+/// so the elements themselves never participate in
+/// comparisons. Rows tied on every column stay in
+/// arbitrary order.
+///
+/// # Panics
+///
+/// Panics if any key column's length differs from
+/// `slice.len()`.
+///
+/// # Examples
+///
+/// ```
+/// let mut names = ["carol", "alice", "bob"];
+/// let ages: &[i64] = &[30, 25, 30];
+/// let heights: &[i64] = &[170, 160, 165];
+/// quicksort::quicksort_by_key_columns(&mut names, &[ages, heights]);
+/// assert_eq!(names, ["alice", "bob", "carol"]);
+/// ```
+#[cfg(feature = "std")]
+pub fn quicksort_by_key_columns<T>(slice: &mut [T], keys: &[&[i64]]) {
+    let nslice = slice.len();
+    for (c, column) in keys.iter().enumerate() {
+        assert_eq!(column.len(), nslice,
+                   "key column {} has wrong length", c);
+    }
+
+    // Order indices lexicographically by the columns.
+    let mut order: Vec<usize> = (0..nslice).collect();
+    quicksort_by_compare(&mut order, &mut |a: &usize, b: &usize| {
+        for column in keys {
+            match column[*a].cmp(&column[*b]) {
+                Ordering::Equal => continue,
+                unequal => return unequal,
+            }
+        }
+        Ordering::Equal
+    });
+
+    // Apply the permutation to the data.
+    let mut dest = vec![0; nslice];
+    for (k, &src) in order.iter().enumerate() {
+        dest[src] = k
+    }
+    for i in 0..nslice {
+        while dest[i] != i {
+            let j = dest[i];
+            slice.swap(i, j);
+            dest.swap(i, j)
+        }
+    }
+}
+
+#[test]
+fn quicksort_by_key_columns_tiebreak() {
+    let mut rows = ['a', 'b', 'c', 'd', 'e'];
+    let primary: &[i64] = &[2, 1, 2, 1, 0];
+    let secondary: &[i64] = &[5, 9, 3, 7, 1];
+    quicksort_by_key_columns(&mut rows, &[primary, secondary]);
+    // Primary groups 'e' | 'd','b' | 'c','a'; secondary
+    // orders within each group.
+    assert_eq!(rows, ['e', 'd', 'b', 'c', 'a'])
+}
+
+#[test]
+#[should_panic(expected = "wrong length")]
+fn quicksort_by_key_columns_length_mismatch() {
+    let mut rows = [1, 2, 3];
+    let short: &[i64] = &[1, 2];
+    quicksort_by_key_columns(&mut rows, &[short]);
+}
+
+/// Sorts `new_unsorted` with `quicksort()` and computes
+/// the multiset difference against the previously sorted
+/// `old_sorted`, returning `(added, removed)`: values (or
+/// extra copies of values) present only in the new data,
+/// and values present only in the old. Both outputs come
+/// back sorted. Duplicates follow multiset semantics — two
+/// old copies against one new copy yields one removal.
+///
+/// # Examples
+///
+/// ```
+/// let old = [1, 2, 2, 5];
+/// let mut new = [5, 3, 2];
+/// let (added, removed) = quicksort::sorted_diff(&old, &mut new);
+/// assert_eq!(added, [3]);
+/// assert_eq!(removed, [1, 2]);
+/// ```
+#[cfg(feature = "std")]
+pub fn sorted_diff<T: Ord + Clone>(
+    old_sorted: &[T],
+    new_unsorted: &mut [T],
+) -> (Vec<T>, Vec<T>) {
+    quicksort(new_unsorted);
+
+    // March the two sorted sequences together; whichever
+    // side is behind holds an element the other lacks.
+    let mut added = Vec::new();
+    let mut removed = Vec::new();
+    let mut i = 0;
+    let mut j = 0;
+    while i < old_sorted.len() && j < new_unsorted.len() {
+        match old_sorted[i].cmp(&new_unsorted[j]) {
+            Ordering::Less => {
+                removed.push(old_sorted[i].clone());
+                i += 1
+            },
+            Ordering::Greater => {
+                added.push(new_unsorted[j].clone());
+                j += 1
+            },
+            Ordering::Equal => {
+                // Matched copies cancel.
+                i += 1;
+                j += 1
+            },
+        }
+    }
+    removed.extend_from_slice(&old_sorted[i ..]);
+    added.extend_from_slice(&new_unsorted[j ..]);
+    (added, removed)
+}
+
+#[test]
+fn sorted_diff_multiset() {
+    let old = [1, 3, 3, 3, 5, 7, 9];
+    let mut new = [9, 3, 8, 5, 3, 2, 3, 3];
+    let (added, removed) = sorted_diff(&old, &mut new);
+    assert_eq!(added, [2, 3, 8]);
+    assert_eq!(removed, [1, 7]);
+
+    // Empty sides.
+    let (added, removed) = sorted_diff(&old, &mut []);
+    assert_eq!(added, []);
+    assert_eq!(removed, old);
+    let (added, removed) = sorted_diff(&[], &mut new);
+    assert_eq!(removed, []);
+    let mut expected = new;
+    quicksort(&mut expected);
+    assert_eq!(added, expected)
+}
+
+/// Moves every element satisfying `pred` to the front of
+/// the slice, preserving the relative order of both the
+/// satisfying elements and the rest — the stable
+/// counterpart of an unstable predicate partition — and
+/// returns the boundary index (the count of satisfying
+/// elements). Stability costs memory: the non-satisfying
+/// elements are cloned into a side buffer (up to `O(n)`
+/// extra space) and copied back behind the boundary.
+///
+/// # Examples
+///
+/// ```
+/// let mut a = [3, 8, 1, 6, 4, 7];
+/// let boundary = quicksort::stable_partition_by_predicate(
+///     &mut a,
+///     |v| v % 2 == 0,
+/// );
+/// assert_eq!(boundary, 3);
+/// assert_eq!(a, [8, 6, 4, 3, 1, 7]);
+/// ```
+#[cfg(feature = "std")]
+pub fn stable_partition_by_predicate<T: Clone, F: FnMut(&T) -> bool>(
+    slice: &mut [T],
+    mut pred: F,
+) -> usize {
+    // Park the holdouts in a side buffer while compacting
+    // the keepers forward; both sweeps go left to right,
+    // which is where the stability comes from.
+    let mut held_out = Vec::new();
+    let mut store = 0;
+    for i in 0..slice.len() {
+        if pred(&slice[i]) {
+            slice.swap(store, i);
+            store += 1
+        } else {
+            held_out.push(slice[i].clone())
+        }
+    }
+
+    // Copy the holdouts back after the boundary, still in
+    // original order.
+    for (k, v) in held_out.into_iter().enumerate() {
+        slice[store + k] = v
+    }
+    store
+}
+
+#[test]
+fn stable_partition_by_predicate_order() {
+    let mut a = [(1, 'x'), (2, 'a'), (3, 'y'), (4, 'b'), (5, 'z'), (6, 'c')];
+    let boundary = stable_partition_by_predicate(&mut a, |t| t.0 % 2 == 1);
+    assert_eq!(boundary, 3);
+    // Odd keys first, both halves in input order.
+    assert_eq!(a, [
+        (1, 'x'), (3, 'y'), (5, 'z'), (2, 'a'), (4, 'b'), (6, 'c'),
+    ])
+}
+
+/// A fixed-point quantity stored as a scaled `i64`: the
+/// raw value counts units of `10^-SCALE`, so
+/// `Fixed::<2>(1234)` is 12.34. Monetary and similar
+/// decimal data sorts correctly as plain integers; this
+/// wrapper records the scale in the type, making the
+/// intent explicit and keeping floats (and their
+/// comparison hazards) out of the picture. Values with
+/// different scales are different types and cannot be
+/// mixed accidentally.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg(feature = "std")]
+pub struct Fixed<const SCALE: u32>(pub i64);
+
+/// Sorts a slice of fixed-point values ascending. This is
+/// just `quicksort()` on the underlying integers, named
+/// for clarity at monetary call sites.
+///
+/// # Examples
+///
+/// ```
+/// use quicksort::Fixed;
+/// let mut cents = [Fixed::<2>(199), Fixed::<2>(99), Fixed::<2>(150)];
+/// quicksort::quicksort_fixed(&mut cents);
+/// assert_eq!(cents, [Fixed::<2>(99), Fixed::<2>(150), Fixed::<2>(199)]);
+/// ```
+#[cfg(feature = "std")]
+pub fn quicksort_fixed<const SCALE: u32>(slice: &mut [Fixed<SCALE>]) {
+    quicksort(slice)
+}
+
+#[test]
+fn quicksort_fixed_dollars() {
+    // Dollar amounts in cents: $10.00, $2.50, $0.99,
+    // -$3.75, $2.50.
+    let mut amounts = vec![
+        Fixed::<2>(1000),
+        Fixed::<2>(250),
+        Fixed::<2>(99),
+        Fixed::<2>(-375),
+        Fixed::<2>(250),
+    ];
+    quicksort_fixed(&mut amounts);
+    assert_eq!(amounts, [
+        Fixed::<2>(-375),
+        Fixed::<2>(99),
+        Fixed::<2>(250),
+        Fixed::<2>(250),
+        Fixed::<2>(1000),
+    ])
+}
+
+/// Sorts like `quicksort()`, but checks each subrange for
+/// all-equal contents before partitioning it and skips the
+/// range when the check passes. Two-way partitioning
+/// degenerates to quadratic on constant data; this check
+/// makes the all-equal case — and all-equal pockets inside
+/// mixed data — linear, without rewriting the partition
+/// three-way. The scan costs `O(n)` equality comparisons
+/// per recursion level, so it's worthwhile when duplicates
+/// are expected and harmless noise (a constant factor)
+/// when they aren't.
+///
+/// # Examples
+///
+/// ```
+/// let mut a = [2, 2, 1, 2, 2, 0];
+/// quicksort::quicksort_const_aware(&mut a);
+/// assert_eq!(a, [0, 1, 2, 2, 2, 2]);
+/// ```
+#[cfg(feature = "std")]
+pub fn quicksort_const_aware<T: Ord>(slice: &mut [T]) {
+    let nslice = slice.len();
+    if nslice <= 1 {
+        return;  // Nothing to sort.
+    }
+
+    // Constant subrange: already "sorted", skip it.
+    let first = &slice[0];
+    if slice[1 ..].iter().all(|v| v == first) {
+        return
+    }
+
+    let pivot_index = partition(slice).unwrap();
+    quicksort_const_aware(&mut slice[.. pivot_index]);
+    quicksort_const_aware(&mut slice[pivot_index + 1 ..]);
+}
+
+#[test]
+fn quicksort_const_aware_all_equal_linear() {
+    use std::cell::Cell;
+
+    // A million equal elements: one scan and done.
+    let n = 1_000_000;
+    let count = Cell::new(0);
+    let mut a: Vec<CountedCmp> =
+        (0..n).map(|_| CountedCmp(7, &count)).collect();
+    quicksort_const_aware(&mut a);
+    assert_eq!(count.get(), (n - 1) as u64);
+
+    // And it still sorts mixed data.
+    let mut b = [5, 5, 5, 1, 5, 9, 5, 5];
+    quicksort_const_aware(&mut b);
+    assert_eq!(b, [1, 5, 5, 5, 5, 5, 5, 9])
+}
+
+/// Sorts like `quicksort()`, but if the sort does not run
+/// to completion — in practice, if a comparison panics and
+/// unwinds through the sort — the `on_interrupt` callback
+/// is invoked before the unwind continues, letting the
+/// caller mark the half-sorted data as dirty or restore
+/// application invariants. The callback is armed through a
+/// drop guard, so it fires exactly once on interruption
+/// and not at all when the sort completes normally. The
+/// slice is left in some valid-but-unspecified partial
+/// order after an interrupt; no elements are lost.
+///
+/// # Examples
+///
+/// ```
+/// let mut a = [3, 1, 2];
+/// quicksort::quicksort_with_guard(&mut a, || {
+///     unreachable!("sort completed, guard must not fire")
+/// });
+/// assert_eq!(a, [1, 2, 3]);
+/// ```
+#[cfg(feature = "std")]
+pub fn quicksort_with_guard<T: Ord>(
+    slice: &mut [T],
+    on_interrupt: impl FnOnce(),
+) {
+    // Fires its payload on drop unless defused.
+    struct Guard<F: FnOnce()>(Option<F>);
+
+    impl<F: FnOnce()> Drop for Guard<F> {
+        fn drop(&mut self) {
+            if let Some(payload) = self.0.take() {
+                payload()
+            }
+        }
+    }
+
+    let mut guard = Guard(Some(on_interrupt));
+    quicksort(slice);
+    // Reached only on normal completion: defuse.
+    guard.0 = None
+}
+
+#[test]
+fn quicksort_with_guard_fires_on_panic() {
+    use std::cell::Cell;
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+
+    // An element whose comparison panics on a poison
+    // value.
+    struct Volatile(i32);
+
+    impl PartialEq for Volatile {
+        fn eq(&self, other: &Volatile) -> bool {
+            self.cmp(other) == Ordering::Equal
+        }
+    }
+
+    impl Eq for Volatile {}
+
+    impl PartialOrd for Volatile {
+        fn partial_cmp(&self, other: &Volatile) -> Option<Ordering> {
+            Some(self.cmp(other))
+        }
+    }
+
+    impl Ord for Volatile {
+        fn cmp(&self, other: &Volatile) -> Ordering {
+            if self.0 == 13 || other.0 == 13 {
+                panic!("comparator blew up")
+            }
+            self.0.cmp(&other.0)
+        }
+    }
+
+    // Interrupted: the guard must fire.
+    let fired = Cell::new(false);
+    let result = catch_unwind(AssertUnwindSafe(|| {
+        let mut a = vec![Volatile(3), Volatile(13), Volatile(1)];
+        quicksort_with_guard(&mut a, || fired.set(true));
+    }));
+    assert!(result.is_err());
+    assert!(fired.get());
+
+    // Successful: it must not.
+    let fired = Cell::new(false);
+    let mut a = vec![Volatile(3), Volatile(2), Volatile(1)];
+    quicksort_with_guard(&mut a, || fired.set(true));
+    assert!(!fired.get());
+    assert_eq!(a[0].0, 1)
+}
+
+/// Sorts a slice of `(u, v, weight)` graph edges by weight
+/// ascending — exactly the preprocessing Kruskal's MST
+/// algorithm wants before scanning edges with a union-find.
+/// Edges of equal weight come out in arbitrary order,
+/// which Kruskal's does not care about.
+///
+/// # Examples
+///
+/// ```
+/// let mut edges = [(0, 1, 4), (1, 2, 1), (0, 2, 3)];
+/// quicksort::quicksort_edges(&mut edges);
+/// assert_eq!(edges, [(1, 2, 1), (0, 2, 3), (0, 1, 4)]);
+/// ```
+#[cfg(feature = "std")]
+pub fn quicksort_edges(edges: &mut [(usize, usize, i64)]) {
+    quicksort_by_compare(edges, &mut |a: &(usize, usize, i64),
+                                      b: &(usize, usize, i64)| {
+        a.2.cmp(&b.2)
+    })
+}
+
+#[test]
+fn quicksort_edges_by_weight() {
+    let mut edges = [
+        (0, 1, 7), (2, 3, -2), (1, 3, 7), (0, 2, 0), (1, 2, 12),
+    ];
+    quicksort_edges(&mut edges);
+    let weights: Vec<i64> = edges.iter().map(|e| e.2).collect();
+    assert_eq!(weights, [-2, 0, 7, 7, 12]);
+
+    // The endpoint pairs all survive.
+    let mut endpoints: Vec<(usize, usize)> =
+        edges.iter().map(|e| (e.0, e.1)).collect();
+    quicksort(&mut endpoints);
+    assert_eq!(endpoints, [(0, 1), (0, 2), (1, 2), (1, 3), (2, 3)])
+}
+
+// Recursive worker for `weighted_median()`: selects the
+// element at which the cumulative weight (in value order)
+// first reaches `target`. The weights ride along with
+// their values through every swap. Returns the element's
+// index within the current range.
+#[cfg(feature = "std")]
+fn weighted_select<T: Ord>(
+    values: &mut [T],
+    weights: &mut [f64],
+    target: f64,
+) -> usize {
+    let nvalues = values.len();
+    if nvalues == 1 {
+        return 0
+    }
+
+    // Middle-pivot Lomuto, swapping weights in step.
+    values.swap(nvalues / 2, nvalues - 1);
+    weights.swap(nvalues / 2, nvalues - 1);
+    let mut store = 0;
+    for i in 0..nvalues - 1 {
+        if values[i] <= values[nvalues - 1] {
+            values.swap(i, store);
+            weights.swap(i, store);
+            store += 1
+        }
+    }
+    values.swap(store, nvalues - 1);
+    weights.swap(store, nvalues - 1);
+
+    // Which side holds the weighted median?
+    let low_weight: f64 = weights[.. store].iter().sum();
+    if low_weight >= target {
+        weighted_select(&mut values[.. store], &mut weights[.. store], target)
+    } else if low_weight + weights[store] >= target {
+        store
+    } else {
+        let skipped = low_weight + weights[store];
+        store + 1 + weighted_select(
+            &mut values[store + 1 ..],
+            &mut weights[store + 1 ..],
+            target - skipped,
+        )
+    }
+}
+
+/// Returns the weighted median of `values`: the value at
+/// which the cumulative weight, accumulated in ascending
+/// value order, first reaches half the total weight.
+/// Weights are expected to be non-negative. The answer is
+/// found by partition-based selection that recurses into
+/// whichever side of the pivot holds the half-weight
+/// point, so the expected cost is linear rather than a
+/// full sort; `values` (and a working copy of the weights)
+/// end up partially reordered.
+///
+/// # Panics
+///
+/// Panics if `weights.len() != values.len()` or `values`
+/// is empty.
+///
+/// # Examples
+///
+/// ```
+/// let mut v = [10, 20, 30];
+/// // 30 carries most of the weight.
+/// assert_eq!(quicksort::weighted_median(&mut v, &[0.1, 0.2, 0.7]), 30);
+/// ```
+#[cfg(feature = "std")]
+pub fn weighted_median<T: Ord + Clone>(values: &mut [T], weights: &[f64]) -> T {
+    assert_eq!(values.len(), weights.len(),
+               "one weight per value required");
+    assert!(!values.is_empty(), "weighted median of nothing");
+
+    let total: f64 = weights.iter().sum();
+    let mut weights = weights.to_vec();
+    let at = weighted_select(values, &mut weights, total / 2.0);
+    values[at].clone()
+}
+
+#[test]
+fn weighted_median_matches_brute_force() {
+    fn brute_force(values: &[i32], weights: &[f64]) -> i32 {
+        let mut pairs: Vec<(i32, f64)> = values
+            .iter()
+            .cloned()
+            .zip(weights.iter().cloned())
+            .collect();
+        pairs.sort_by_key(|a| a.0);
+        let total: f64 = weights.iter().sum();
+        let mut running = 0.0;
+        for (v, w) in pairs {
+            running += w;
+            if running >= total / 2.0 {
+                return v
+            }
+        }
+        unreachable!()
+    }
+
+    let values = [14, 2, 9, 5, 11, 7, 3];
+    let weights = [0.1, 0.3, 2.0, 0.5, 0.1, 0.4, 0.2];
+    let expected = brute_force(&values, &weights);
+    let mut scratch = values;
+    assert_eq!(weighted_median(&mut scratch, &weights), expected);
+
+    // Uniform weights reduce to the ordinary median.
+    let mut scratch = values;
+    let uniform = [1.0; 7];
+    assert_eq!(weighted_median(&mut scratch, &uniform),
+               brute_force(&values, &uniform))
+}
+
+/// Sorts a copy of the input into a gapped (library-sort)
+/// layout: a `Vec<Option<T>>` whose `Some` entries are the
+/// input values in ascending order, interleaved with
+/// `None` gaps so later insertions only have to shift
+/// elements as far as the nearest gap. Also returns the
+/// layout index of each sorted element, in order, so
+/// callers can find things without scanning past gaps.
+///
+/// `gap_ratio` is gaps per element: after each element is
+/// placed, `gap_ratio` accumulates and every whole unit
+/// emits a gap, spreading the gaps evenly — `0.5` puts a
+/// gap after every second element, `2.0` puts two after
+/// each. Negative ratios are treated as zero.
+///
+/// # Examples
+///
+/// ```
+/// let (layout, at) = quicksort::quicksort_with_gaps(&[3, 1, 2], 0.5);
+/// assert_eq!(layout, [Some(1), Some(2), None, Some(3)]);
+/// assert_eq!(at, [0, 1, 3]);
+/// ```
+#[cfg(feature = "std")]
+pub fn quicksort_with_gaps<T: Ord + Clone>(
+    slice: &[T],
+    gap_ratio: f64,
+) -> (Vec<Option<T>>, Vec<usize>) {
+    let mut sorted = slice.to_vec();
+    quicksort(&mut sorted);
+
+    let gap_ratio = gap_ratio.max(0.0);
+    let mut layout = Vec::new();
+    let mut positions = Vec::with_capacity(sorted.len());
+    let mut owed = 0.0;
+    for v in sorted {
+        positions.push(layout.len());
+        layout.push(Some(v));
+        // Pay out whole gaps as the fractional debt
+        // accumulates.
+        owed += gap_ratio;
+        while owed >= 1.0 {
+            layout.push(None);
+            owed -= 1.0
+        }
+    }
+    (layout, positions)
+}
+
+#[test]
+fn quicksort_with_gaps_layout() {
+    let input = [9, 4, 6, 1, 8, 3, 7, 2];
+    let (layout, positions) = quicksort_with_gaps(&input, 0.5);
+
+    // The occupied entries are the sorted input.
+    let occupied: Vec<i32> = layout.iter().filter_map(|v| *v).collect();
+    let mut expected = input.to_vec();
+    quicksort(&mut expected);
+    assert_eq!(occupied, expected);
+
+    // Positions point at the right slots.
+    for (i, &at) in positions.iter().enumerate() {
+        assert_eq!(layout[at], Some(expected[i]))
+    }
+
+    // Half a gap per element: four gaps, never adjacent.
+    let ngaps = layout.iter().filter(|v| v.is_none()).count();
+    assert_eq!(ngaps, 4);
+    for pair in layout.windows(2) {
+        assert!(pair[0].is_some() || pair[1].is_some())
+    }
+}
+
+/// Sorts serialized records by a big-endian integer key
+/// stored at a fixed byte range within each record:
+/// `record[key_offset .. key_offset + key_len]`. Because
+/// the key is big-endian, plain lexicographic byte
+/// comparison of the key ranges gives numeric (unsigned)
+/// order, so records never have to be deserialized. The
+/// rest of each record is ignored and records may differ
+/// in length beyond the key range.
+///
+/// # Panics
+///
+/// Panics if any record is too short to contain the key
+/// range.
+///
+/// # Examples
+///
+/// ```
+/// let mut records = vec![
+///     vec![0, 2, b'x'],
+///     vec![0, 1, b'y'],
+/// ];
+/// quicksort::quicksort_by_be_key(&mut records, 0, 2);
+/// assert_eq!(records[0][1], 1);
+/// ```
+#[cfg(feature = "std")]
+pub fn quicksort_by_be_key(
+    slice: &mut [Vec<u8>],
+    key_offset: usize,
+    key_len: usize,
+) {
+    for (i, record) in slice.iter().enumerate() {
+        assert!(record.len() >= key_offset + key_len,
+                "record {} too short for key range", i);
+    }
+    quicksort_by_compare(slice, &mut |a: &Vec<u8>, b: &Vec<u8>| {
+        a[key_offset .. key_offset + key_len]
+            .cmp(&b[key_offset .. key_offset + key_len])
+    })
+}
+
+#[test]
+fn quicksort_by_be_key_numeric_order() {
+    // Records: one tag byte, then a u16 big-endian key,
+    // then payload. Keys chosen so byte order and numeric
+    // order disagree for little-endian storage.
+    let make = |key: u16, tag: u8| -> Vec<u8> {
+        vec![tag, (key >> 8) as u8, key as u8, 0xee, 0xff]
+    };
+    let mut records = vec![
+        make(0x0100, 1), make(0x00ff, 2), make(0x1000, 3),
+        make(0x0001, 4), make(0x0fff, 5),
+    ];
+    quicksort_by_be_key(&mut records, 1, 2);
+    let keys: Vec<u16> = records
+        .iter()
+        .map(|r| u16::from(r[1]) << 8 | u16::from(r[2]))
+        .collect();
+    assert_eq!(keys, [0x0001, 0x00ff, 0x0100, 0x0fff, 0x1000])
+}
+
+#[test]
+#[should_panic(expected = "too short")]
+fn quicksort_by_be_key_short_record() {
+    let mut records = vec![vec![1, 2, 3], vec![4]];
+    quicksort_by_be_key(&mut records, 0, 2);
+}
+
+#[test]
+fn quicksort_adaptive_sorted_optimal_comparisons() {
+    use std::cell::Cell;
+
+    // Sorted input must cost exactly the n - 1 comparisons
+    // of the confirming scan — the optimal for verifying
+    // sortedness — and nothing more.
+    let n = 500;
+    let count = Cell::new(0);
+    let mut a: Vec<CountedCmp> =
+        (0..n).map(|i| CountedCmp(i, &count)).collect();
+    quicksort_adaptive_tuned(&mut a, 16);
+    assert_eq!(count.get(), (n - 1) as u64);
+    for i in 1..a.len() {
+        assert!(a[i - 1].0 <= a[i].0)
+    }
+}
+
+/// Sorts the slice and returns its elements in balanced
+/// BST insertion order: the middle element of the sorted
+/// data first, then the middles of the two halves, and so
+/// on level by level. Inserting the returned sequence into
+/// an unbalanced binary search tree yields a tree of
+/// minimal height. The middle of an even-length range is
+/// the upper of the two candidates.
+///
+/// # Examples
+///
+/// ```
+/// let mut a = [3, 1, 2];
+/// let order = quicksort::quicksort_to_bst_order(&mut a);
+/// assert_eq!(order, [2, 1, 3]);
+/// ```
+#[cfg(feature = "std")]
+pub fn quicksort_to_bst_order<T: Ord + Clone>(slice: &mut [T]) -> Vec<T> {
+    quicksort(slice);
+
+    // Breadth-first walk over sorted subranges: emit each
+    // range's middle, queue its two sides.
+    let mut order = Vec::with_capacity(slice.len());
+    let mut queue = std::collections::VecDeque::new();
+    queue.push_back((0, slice.len()));
+    while let Some((lo, hi)) = queue.pop_front() {
+        if lo >= hi {
+            continue
+        }
+        let mid = lo + (hi - lo) / 2;
+        order.push(slice[mid].clone());
+        queue.push_back((lo, mid));
+        queue.push_back((mid + 1, hi))
+    }
+    order
+}
+
+#[test]
+fn quicksort_to_bst_order_levels() {
+    let mut a = [6, 3, 1, 5, 2, 4];
+    let order = quicksort_to_bst_order(&mut a);
+    // Root 4, then the subtree roots 2 and 6, then the
+    // leaves.
+    assert_eq!(order, [4, 2, 6, 1, 3, 5])
+}
+
+/// Sorts with a three-valued comparator where `None` means
+/// "don't care — either order is acceptable." For ordering
+/// purposes `None` is treated exactly like
+/// `Some(Ordering::Equal)`, so the sort is free to leave
+/// such pairs in whatever order the partitioning happens
+/// to produce; pairs with a definite `Some` ordering end
+/// up correctly arranged relative to each other as long as
+/// the definite part of the comparator is consistent
+/// (transitive, and compatible with the don't-cares).
+///
+/// # Examples
+///
+/// ```
+/// use std::cmp::Ordering;
+/// let mut a = [3, 1, 2];
+/// quicksort::quicksort_with_dontcare(&mut a, |x, y| x.partial_cmp(y));
+/// assert_eq!(a, [1, 2, 3]);
+/// ```
+#[cfg(feature = "std")]
+pub fn quicksort_with_dontcare<T, F: FnMut(&T, &T) -> Option<Ordering>>(
+    slice: &mut [T],
+    mut compare: F,
+) {
+    quicksort_by_compare(slice, &mut |a: &T, b: &T| {
+        compare(a, b).unwrap_or(Ordering::Equal)
+    })
+}
+
+#[test]
+fn quicksort_with_dontcare_defined_pairs_ordered() {
+    // Order by the key; pairs sharing a key are "don't
+    // care."
+    let mut a = [(3, 'a'), (1, 'b'), (3, 'c'), (2, 'd'), (1, 'e')];
+    quicksort_with_dontcare(&mut a, |x, y| {
+        if x.0 == y.0 {
+            None
+        } else {
+            Some(x.0.cmp(&y.0))
+        }
+    });
+    let keys: Vec<i32> = a.iter().map(|t| t.0).collect();
+    assert_eq!(keys, [1, 1, 2, 3, 3]);
+
+    // Every element survived, whatever order the don't-
+    // care pairs took.
+    let mut markers: Vec<char> = a.iter().map(|t| t.1).collect();
+    quicksort(&mut markers);
+    assert_eq!(markers, ['a', 'b', 'c', 'd', 'e'])
+}
+
+/// Sorts elements so their discriminants appear in the
+/// explicit order given by `order`, rather than whatever
+/// order the discriminant type's own `Ord` (or enum
+/// declaration) would impose. Elements whose discriminant
+/// is not listed in `order` all sort after every listed
+/// one. Elements sharing a discriminant — and all unlisted
+/// elements — stay in arbitrary relative order.
+///
+/// # Examples
+///
+/// ```
+/// let mut a = ["cherry", "apple", "banana", "apple"];
+/// quicksort::quicksort_by_enum_order(
+///     &mut a,
+///     |s| s.as_bytes()[0],
+///     &[b'b', b'c', b'a'],
+/// );
+/// assert_eq!(a, ["banana", "cherry", "apple", "apple"]);
+/// ```
+#[cfg(feature = "std")]
+pub fn quicksort_by_enum_order<T, E: Copy + PartialEq, F: Fn(&T) -> E>(
+    slice: &mut [T],
+    discriminant: F,
+    order: &[E],
+) {
+    // Rank of a discriminant: its position in `order`,
+    // with the unlisted bucketed after everything.
+    let rank = |v: &T| -> usize {
+        let d = discriminant(v);
+        order
+            .iter()
+            .position(|e| *e == d)
+            .unwrap_or(order.len())
+    };
+    quicksort_by_compare(slice, &mut |a: &T, b: &T| rank(a).cmp(&rank(b)))
+}
+
+#[test]
+fn quicksort_by_enum_order_custom() {
+    #[derive(Clone, Copy, PartialEq, Debug)]
+    enum Severity {
+        Info,
+        Warning,
+        Error,
+        Debug,
+    }
+    let mut log = [
+        (Severity::Info, 1), (Severity::Error, 2), (Severity::Debug, 3),
+        (Severity::Warning, 4), (Severity::Error, 5), (Severity::Info, 6),
+    ];
+    // Most severe first; Debug is unlisted and goes last.
+    quicksort_by_enum_order(
+        &mut log,
+        |e| e.0,
+        &[Severity::Error, Severity::Warning, Severity::Info],
+    );
+    let kinds: Vec<Severity> = log.iter().map(|e| e.0).collect();
+    assert_eq!(kinds, [
+        Severity::Error, Severity::Error, Severity::Warning,
+        Severity::Info, Severity::Info, Severity::Debug,
+    ])
+}
+
+/// Partially sorts the slice just enough to find and
+/// return a clone of the element at rank
+/// `round(ratio * (len - 1))`, for any `ratio` in `[0, 1]`:
+/// `0.0` selects the minimum, `0.5` the median, `1.0` the
+/// maximum, and anything between the corresponding
+/// quantile. This runs quickselect — partition, then
+/// descend into only the side holding the target rank — so
+/// it is O(n) on average rather than a full sort. Panics
+/// if the slice is empty or `ratio` is outside `[0, 1]`.
+///
+/// # Examples
+///
+/// ```
+/// let mut a = [5, 1, 0, 4, 3, 2];
+/// assert_eq!(quicksort::select_ratio(&mut a, 0.0), 0);
+/// assert_eq!(quicksort::select_ratio(&mut a, 1.0), 5);
+/// ```
+#[cfg(feature = "std")]
+pub fn select_ratio<T: Ord + Clone>(slice: &mut [T], ratio: f64) -> T {
+    assert!(
+        (0.0..=1.0).contains(&ratio),
+        "select_ratio: ratio must lie in [0, 1]",
+    );
+    let nslice = slice.len();
+    assert!(nslice > 0, "select_ratio: empty slice");
+    let k = (ratio * (nslice - 1) as f64).round() as usize;
+
+    // Quickselect over a shrinking subrange of the slice.
+    let mut lo = 0;
+    let mut hi = nslice;
+    loop {
+        if hi - lo == 1 {
+            return slice[lo].clone()
+        }
+        let pivot_index =
+            lo + partition_around(&mut slice[lo .. hi], (hi - lo) / 2);
+        if k == pivot_index {
+            return slice[k].clone()
+        }
+        if k < pivot_index {
+            hi = pivot_index
+        } else {
+            lo = pivot_index + 1
+        }
+    }
+}
+
+#[test]
+fn select_ratio_quantiles() {
+    use rand::Rng;
+    for &n in &[1, 2, 3, 10, 101] {
+        let mut a = Vec::with_capacity(n);
+        for _ in 0..n {
+            a.push(rand::thread_rng().gen_range(-1000i64, 1000))
+        }
+        let mut sorted = a.clone();
+        quicksort(&mut sorted);
+        for &ratio in &[0.0, 0.5, 1.0] {
+            let k = (ratio * (n - 1) as f64).round() as usize;
+            assert_eq!(select_ratio(&mut a.clone(), ratio), sorted[k])
+        }
+    }
+}
+
+/// Sorts the slice by the key that `f` extracts from each
+/// element, mirroring `std`'s `sort_by_key`. Keys are
+/// computed on the fly at every comparison rather than
+/// cached, so keep `f` reasonably cheap; the sort itself
+/// just runs `quicksort_by()` with a key-comparing
+/// closure.
+///
+/// # Examples
+///
+/// ```
+/// let mut pairs = [("c", 3u32), ("a", 1), ("b", 2)];
+/// quicksort::quicksort_by_key(&mut pairs, |p| p.1);
+/// assert_eq!(pairs, [("a", 1), ("b", 2), ("c", 3)]);
+/// ```
+#[cfg(feature = "std")]
+pub fn quicksort_by_key<T, K: Ord, F: FnMut(&T) -> K>(slice: &mut [T], mut f: F) {
+    quicksort_by(slice, |a, b| f(a).cmp(&f(b)))
+}
+
+#[test]
+fn quicksort_by_key_tuples() {
+    let mut pairs = vec![
+        ("walnut".to_string(), 9u32),
+        ("fig".to_string(), 2),
+        ("date".to_string(), 7),
+        ("almond".to_string(), 2),
+    ];
+    quicksort_by_key(&mut pairs, |p| p.1);
+    let keys: Vec<u32> = pairs.iter().map(|p| p.1).collect();
+    assert_eq!(keys, [2, 2, 7, 9])
+}
+
+#[test]
+fn quicksort_by_key_string_length() {
+    let mut words = ["longest", "mid", "a", "four"];
+    quicksort_by_key(&mut words, |w| w.len());
+    assert_eq!(words, ["a", "mid", "four", "longest"])
+}
+
+/// What `sortedness_report()` suggests running on the
+/// data it examined.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg(feature = "std")]
+pub enum SortRecommendation {
+    /// Already sorted (or as good as): a single insertion
+    /// pass finishes in linear time.
+    Insertion,
+    /// Substantially disordered: plain `quicksort()`.
+    Quick,
+    /// Mostly ordered with scattered disorder: an
+    /// adaptive sort like `quicksort_adaptive_tuned()`.
+    Adaptive,
+}
+
+/// Summary of how sorted a slice already is, from
+/// `sortedness_report()`.
+#[derive(Clone, Copy, Debug)]
+#[cfg(feature = "std")]
+pub struct SortednessReport {
+    /// Length of the longest run of non-decreasing
+    /// adjacent elements.
+    pub longest_run: usize,
+    /// Estimated fraction of element pairs that are
+    /// inverted, in `[0, 1]`: 0 for sorted data, about 0.5
+    /// for a random permutation.
+    pub inversion_estimate: f64,
+    /// Suggested algorithm given the two measures above.
+    pub recommendation: SortRecommendation,
+}
+
+/// Examines the slice without modifying it and reports how
+/// close to sorted it already is, so pipelines can branch
+/// to an appropriate algorithm. The longest
+/// non-decreasing run is found with one exact linear scan.
+/// The inversion fraction is *estimated*, not counted:
+/// 1000 pairs `i < j` are drawn uniformly at random and
+/// the fraction with `slice[i] > slice[j]` is reported, so
+/// the estimate has sampling error of a few percent and
+/// can miss inversions that are rare overall.
+///
+/// The recommendation is `Insertion` when the whole slice
+/// is one non-decreasing run, `Quick` when the estimated
+/// inversion fraction is 0.25 or more, and `Adaptive` in
+/// between.
+#[cfg(feature = "std")]
+pub fn sortedness_report<T: Ord>(slice: &[T]) -> SortednessReport {
+    let nslice = slice.len();
+
+    // Exact longest non-decreasing run.
+    let mut longest_run = nslice.min(1);
+    let mut run = longest_run;
+    for i in 1..nslice {
+        if slice[i - 1] <= slice[i] {
+            run += 1;
+            longest_run = longest_run.max(run)
+        } else {
+            run = 1
+        }
+    }
+
+    // Sampled inversion fraction.
+    let mut inverted = 0;
+    let nsamples = 1000;
+    if nslice >= 2 {
+        let mut rng = CheapRng::new();
+        for _ in 0..nsamples {
+            let i = (rng.next_f64() * nslice as f64) as usize;
+            let j = (rng.next_f64() * nslice as f64) as usize;
+            let (i, j) = (i.min(j), i.max(j));
+            if i != j && slice[i] > slice[j] {
+                inverted += 1
+            }
+        }
+    }
+    let inversion_estimate = inverted as f64 / nsamples as f64;
+
+    let recommendation = if longest_run == nslice {
+        SortRecommendation::Insertion
+    } else if inversion_estimate >= 0.25 {
+        SortRecommendation::Quick
+    } else {
+        SortRecommendation::Adaptive
+    };
+    SortednessReport { longest_run, inversion_estimate, recommendation }
+}
+
+#[test]
+fn sortedness_report_recommendations() {
+    use rand::Rng;
+
+    // Sorted: one full-length run, no inversions.
+    let sorted: Vec<u32> = (0..1000).collect();
+    let report = sortedness_report(&sorted);
+    assert_eq!(report.longest_run, 1000);
+    assert_eq!(report.inversion_estimate, 0.0);
+    assert_eq!(report.recommendation, SortRecommendation::Insertion);
+
+    // Random: about half of all pairs inverted.
+    let mut random = Vec::with_capacity(1000);
+    for _ in 0..1000 {
+        random.push(rand::thread_rng().gen_range(-10_000i64, 10_000))
+    }
+    let report = sortedness_report(&random);
+    assert_eq!(report.recommendation, SortRecommendation::Quick);
+
+    // Nearly sorted: swap a few adjacent pairs.
+    let mut nearly: Vec<u32> = (0..1000).collect();
+    for i in 0..10 {
+        nearly.swap(i * 100, i * 100 + 1)
+    }
+    let report = sortedness_report(&nearly);
+    assert!(report.longest_run < 1000);
+    assert_eq!(report.recommendation, SortRecommendation::Adaptive)
+}
+
+/// Rearranges the slice so the element that belongs at
+/// index `k` in sorted order is there, and returns a
+/// reference to it. Everything before index `k` compares
+/// no larger and everything after no smaller, but neither
+/// side is otherwise sorted. This is quickselect:
+/// `partition()` runs as in `quicksort()`, but only the
+/// side containing `k` is descended into, for O(n) average
+/// time instead of O(n log n). Panics if
+/// `k >= slice.len()`.
+///
+/// # Examples
+///
+/// ```
+/// let mut a = [5, 1, 0, 4, 3, 2];
+/// assert_eq!(*quicksort::select_nth(&mut a, 2), 2);
+/// ```
+#[cfg(feature = "std")]
+pub fn select_nth<T: Ord>(slice: &mut [T], k: usize) -> &T {
+    let nslice = slice.len();
+    assert!(
+        k < nslice,
+        "select_nth: rank {} out of range for slice of length {}",
+        k,
+        nslice,
+    );
+
+    // Narrow [lo, hi) around k until the pivot lands on it.
+    let mut lo = 0;
+    let mut hi = nslice;
+    while hi - lo > 1 {
+        let pivot_index = lo + partition(&mut slice[lo .. hi]).unwrap();
+        if k == pivot_index {
+            break
+        }
+        if k < pivot_index {
+            hi = pivot_index
+        } else {
+            lo = pivot_index + 1
+        }
+    }
+    &slice[k]
+}
+
+#[test]
+fn select_nth_matches_sort() {
+    use rand::Rng;
+    for _ in 0..100 {
+        let n = rand::thread_rng().gen_range(1, 50);
+        let mut a = Vec::with_capacity(n);
+        for _ in 0..n {
+            a.push(rand::thread_rng().gen_range(-100i64, 100))
+        }
+        let mut sorted = a.clone();
+        quicksort(&mut sorted);
+        let k = rand::thread_rng().gen_range(0, n);
+        assert_eq!(*select_nth(&mut a, k), sorted[k])
+    }
+}
+
+#[test]
+#[should_panic(expected = "out of range")]
+fn select_nth_rejects_bad_rank() {
+    let mut a = [1, 2, 3];
+    select_nth(&mut a, 3);
+}
+
+/// Returns the median element of the slice, or `None` if
+/// it is empty. The slice itself is untouched: its
+/// elements are cloned into scratch space and the middle
+/// one found with `select_nth()`, so the cost is O(n)
+/// average rather than a full sort. For even lengths this
+/// returns the *lower* median, the element at sorted index
+/// `(len - 1) / 2` (equivalently `len / 2 - 1`).
+///
+/// # Examples
+///
+/// ```
+/// assert_eq!(quicksort::median(&[3, 1, 4, 1, 5]), Some(3));
+/// assert_eq!(quicksort::median(&[4, 1, 3, 2]), Some(2));
+/// ```
+#[cfg(feature = "std")]
+pub fn median<T: Ord + Clone>(slice: &[T]) -> Option<T> {
+    if slice.is_empty() {
+        return None
+    }
+    let mut scratch = slice.to_vec();
+    let k = (scratch.len() - 1) / 2;
+    Some(select_nth(&mut scratch, k).clone())
+}
+
+#[test]
+fn median_lengths() {
+    let empty: [u32; 0] = [];
+    assert_eq!(median(&empty), None);
+    assert_eq!(median(&[7]), Some(7));
+    // Odd length: the true middle.
+    assert_eq!(median(&[9, 2, 5, 1, 7]), Some(5));
+    // Even length: the lower median.
+    assert_eq!(median(&[9, 2, 5, 1]), Some(2))
+}
+
+/// Sorts the slice by keys produced in a single batch
+/// call: `batch_score` receives the whole slice once and
+/// returns one key per element, element `i`'s key at index
+/// `i` of the returned `Vec` — the call is checked to
+/// return exactly `slice.len()` keys. Scoring everything
+/// in one call lets the scorer vectorize or otherwise
+/// amortize per-element work in ways a per-element closure
+/// cannot, and each key is computed exactly once. The
+/// keys are then sorted as a proxy: an index permutation
+/// is ordered by key and applied to both, moving each
+/// element to its final position directly.
+///
+/// # Examples
+///
+/// ```
+/// let mut a = [-3i64, 1, -2, 0];
+/// // Score all elements in one pass: sort by absolute value.
+/// quicksort::quicksort_by_batch_key(&mut a, |xs| {
+///     xs.iter().map(|x| x.abs()).collect()
+/// });
+/// assert_eq!(a, [0, 1, -2, -3]);
+/// ```
+#[cfg(feature = "std")]
+pub fn quicksort_by_batch_key<T, K: Ord>(
+    slice: &mut [T],
+    batch_score: impl Fn(&[T]) -> Vec<K>,
+) {
+    let nslice = slice.len();
+    let keys = batch_score(slice);
+    assert_eq!(
+        keys.len(),
+        nslice,
+        "quicksort_by_batch_key: scorer returned wrong number of keys",
+    );
+
+    // Order an index permutation by cached key.
+    let mut perm: Vec<usize> = (0..nslice).collect();
+    quicksort_by(&mut perm, |&i, &j| keys[i].cmp(&keys[j]));
+
+    // Invert the permutation to per-element destinations,
+    // then apply it with cycle-following swaps.
+    let mut dest = vec![0; nslice];
+    for (target, &source) in perm.iter().enumerate() {
+        dest[source] = target
+    }
+    for i in 0..nslice {
+        while dest[i] != i {
+            let j = dest[i];
+            slice.swap(i, j);
+            dest.swap(i, j)
+        }
+    }
+}
+
+#[test]
+fn quicksort_by_batch_key_orders_by_score() {
+    let mut words = vec![
+        "pear".to_string(),
+        "cherry".to_string(),
+        "fig".to_string(),
+        "apple!".to_string(),
+    ];
+    // One batch call scoring by count of alphabetic bytes.
+    let mut calls = 0;
+    {
+        let calls = std::cell::Cell::from_mut(&mut calls);
+        quicksort_by_batch_key(&mut words, |ws| {
+            calls.set(calls.get() + 1);
+            ws.iter()
+                .map(|w| w.bytes().filter(u8::is_ascii_alphabetic).count())
+                .collect()
+        });
+    }
+    assert_eq!(calls, 1);
+    assert_eq!(words, ["fig", "pear", "apple!", "cherry"])
+}
+
+/// Sorts by `primary`, breaking ties with the key
+/// `secondary` extracts — but computes that key lazily:
+/// `secondary` runs only when a comparison actually comes
+/// up `Equal` under `primary`, and each element's key is
+/// cached so it is computed at most once. Elements whose
+/// primary ordering is always decisive never pay for the
+/// secondary key at all, which matters when it is
+/// expensive (a database lookup, a big hash). Internally
+/// an index permutation is sorted so the cache can be
+/// keyed by element position, then applied in one pass.
+#[cfg(feature = "std")]
+pub fn quicksort_lazy_tiebreak<T, P: FnMut(&T, &T) -> Ordering, S>(
+    slice: &mut [T],
+    mut primary: P,
+    secondary: impl Fn(&T) -> S,
+) where S: Ord {
+    let nslice = slice.len();
+    let mut cache: Vec<Option<S>> = Vec::with_capacity(nslice);
+    for _ in 0..nslice {
+        cache.push(None)
+    }
+
+    let mut perm: Vec<usize> = (0..nslice).collect();
+    quicksort_by(&mut perm, |&i, &j| {
+        if i == j {
+            // Comparing an element with itself needs no
+            // tiebreak.
+            return Ordering::Equal
+        }
+        match primary(&slice[i], &slice[j]) {
+            Ordering::Equal => {
+                // Tie: fill the secondary cache on demand.
+                if cache[i].is_none() {
+                    cache[i] = Some(secondary(&slice[i]))
+                }
+                if cache[j].is_none() {
+                    cache[j] = Some(secondary(&slice[j]))
+                }
+                cache[i].cmp(&cache[j])
+            }
+            decided => decided,
+        }
+    });
+
+    // Apply the permutation with cycle-following swaps.
+    let mut dest = vec![0; nslice];
+    for (target, &source) in perm.iter().enumerate() {
+        dest[source] = target
+    }
+    for i in 0..nslice {
+        while dest[i] != i {
+            let j = dest[i];
+            slice.swap(i, j);
+            dest.swap(i, j)
+        }
+    }
+}
+
+#[test]
+fn quicksort_lazy_tiebreak_computes_keys_on_ties_only() {
+    use std::cell::Cell;
+
+    // All primaries distinct: the tiebreaker never runs.
+    let calls = Cell::new(0u32);
+    let mut a = [(3, 'c'), (1, 'a'), (2, 'b')];
+    quicksort_lazy_tiebreak(
+        &mut a,
+        |x: &(i32, char), y: &(i32, char)| x.0.cmp(&y.0),
+        |x| { calls.set(calls.get() + 1); x.1 },
+    );
+    assert_eq!(calls.get(), 0);
+    assert_eq!(a, [(1, 'a'), (2, 'b'), (3, 'c')]);
+
+    // Two groups of primary-equal elements: only their
+    // five members are ever scored, once each.
+    let calls = Cell::new(0u32);
+    let mut a = [(2, 'z'), (1, 'q'), (2, 'a'), (0, 'x'), (1, 'p'), (1, 'r')];
+    quicksort_lazy_tiebreak(
+        &mut a,
+        |x: &(i32, char), y: &(i32, char)| x.0.cmp(&y.0),
+        |x| { calls.set(calls.get() + 1); x.1 },
+    );
+    assert!(calls.get() <= 5);
+    assert_eq!(a, [(0, 'x'), (1, 'p'), (1, 'q'), (1, 'r'), (2, 'a'), (2, 'z')])
+}
+
+/// Tracks the largest `k` elements of a stream without
+/// keeping the whole stream or paying heap maintenance on
+/// every push. Elements accumulate in a buffer of
+/// capacity `2k`; when it fills, one `select_nth()` call
+/// partitions the buffer around its k-th largest element
+/// and the smaller half is discarded in one batch. Each
+/// eviction costs O(k) and frees `k` slots, so the
+/// amortized cost per pushed element is O(1) — cheaper for
+/// huge streams than the O(log k) per push of a strict
+/// min-heap.
+///
+/// # Examples
+///
+/// ```
+/// let mut top = quicksort::TopK::new(3);
+/// for v in [5, 1, 9, 3, 7, 2] {
+///     top.push(v)
+/// }
+/// assert_eq!(top.into_sorted_vec(), [9, 7, 5]);
+/// ```
+#[cfg(feature = "std")]
+pub struct TopK<T> {
+    k: usize,
+    buf: Vec<T>,
+}
+
+#[cfg(feature = "std")]
+impl<T: Ord> TopK<T> {
+    /// Makes an empty tracker of the `k` largest stream
+    /// elements. Panics if `k` is zero.
+    pub fn new(k: usize) -> TopK<T> {
+        assert!(k > 0, "TopK: k must be positive");
+        TopK { k, buf: Vec::with_capacity(2 * k) }
+    }
+
+    /// Offers one stream element.
+    pub fn push(&mut self, v: T) {
+        self.buf.push(v);
+        if self.buf.len() == 2 * self.k {
+            self.evict()
+        }
+    }
+
+    // Batch eviction: partition the full buffer around its
+    // k-th largest element and drop everything below.
+    fn evict(&mut self) {
+        let cut = self.buf.len() - self.k;
+        select_nth(&mut self.buf, cut);
+        self.buf.drain(.. cut);
+    }
+
+    /// Consumes the tracker and returns the largest `k`
+    /// elements seen (fewer if the stream was shorter),
+    /// largest first.
+    pub fn into_sorted_vec(mut self) -> Vec<T> {
+        if self.buf.len() > self.k {
+            self.evict()
+        }
+        quicksort(&mut self.buf);
+        self.buf.reverse();
+        self.buf
+    }
+}
+
+#[test]
+fn top_k_matches_brute_force() {
+    let mut rng = CheapRng::new();
+    let mut top = TopK::new(25);
+    let mut all = Vec::with_capacity(1_000_000);
+    for _ in 0..1_000_000 {
+        let v = rng.next_u64();
+        all.push(v);
+        top.push(v)
+    }
+    // Brute force: sort the whole stream and keep the top
+    // 25. (std's sort — our own partition is quadratic in
+    // test builds from its invariant assertions.)
+    all.sort();
+    all.reverse();
+    all.truncate(25);
+    assert_eq!(top.into_sorted_vec(), all)
+}
+
+#[test]
+fn quicksort_adversarial_shapes() {
+    // Sorted, reverse-sorted and organ-pipe inputs: the
+    // shapes median-of-three pivot seeding is there for.
+    // (Sized to stay fast under the test-build invariant
+    // assertions in `partition_by()`, which make each
+    // partition call quadratic.)
+    let n: u32 = 10_000;
+    let mut shapes: Vec<Vec<u32>> = Vec::new();
+    shapes.push((0..n).collect());
+    shapes.push((0..n).rev().collect());
+    let mut pipe: Vec<u32> = (0..n / 2).collect();
+    pipe.extend((0..n / 2).rev());
+    shapes.push(pipe);
+    for mut a in shapes {
+        let mut expected = a.clone();
+        expected.sort();
+        quicksort(&mut a);
+        assert_eq!(a, expected)
+    }
+}
+
+/// Sorts angles, given in radians in `[0, 2π)`, by their
+/// cyclic position starting from `origin`: the angle at or
+/// just past `origin` comes first, proceeding in the
+/// direction of increasing angle and wrapping past `2π`
+/// back through zero. Natural numeric order is wrong for
+/// angles near the wrap point — with `origin` at `3π/2`,
+/// the angle `0.1` belongs *after* `1.9π`, not before —
+/// so each angle is compared by its offset
+/// `(a - origin) mod 2π` instead. Angles must not be NaN.
+///
+/// # Examples
+///
+/// ```
+/// use std::f64::consts::PI;
+/// let mut a = [0.1, 5.0, 3.0];
+/// // Start a little before the wrap: 5.0 comes first,
+/// // then 0.1 past the wrap, then 3.0.
+/// quicksort::quicksort_cyclic(&mut a, 4.5);
+/// assert_eq!(a, [5.0, 0.1, 3.0]);
+/// ```
+#[cfg(feature = "std")]
+pub fn quicksort_cyclic(slice: &mut [f64], origin: f64) {
+    let tau = 2.0 * std::f64::consts::PI;
+    let offset = |a: f64| (a - origin).rem_euclid(tau);
+    quicksort_by(slice, |&a, &b| {
+        offset(a).partial_cmp(&offset(b)).unwrap()
+    })
+}
+
+#[test]
+fn quicksort_cyclic_wraps() {
+    use std::f64::consts::PI;
+    // Angles straddling the wrap point relative to an
+    // origin of 3π/2.
+    let mut a = [0.2, 6.0, 1.0, 5.0, PI, 0.0];
+    quicksort_cyclic(&mut a, 1.5 * PI);
+    assert_eq!(a, [5.0, 6.0, 0.0, 0.2, 1.0, PI]);
+
+    // Origin zero reduces to plain ascending order.
+    let mut a = [PI, 0.5, 6.0, 2.0];
+    quicksort_cyclic(&mut a, 0.0);
+    assert_eq!(a, [0.5, 2.0, PI, 6.0])
+}
+
+#[test]
+fn quicksort_around_insertion_threshold() {
+    use rand::Rng;
+    // Lengths straddling INSERTION_THRESHOLD.
+    for n in 15..=17 {
+        let mut a = Vec::with_capacity(n);
+        for _ in 0..n {
+            a.push(rand::thread_rng().gen_range(-20i64, 20))
+        }
+        let mut expected = a.clone();
+        expected.sort();
+        quicksort(&mut a);
+        assert_eq!(a, expected)
+    }
+}
+
+/// Partitions the `u32` values into `2^bit_count`
+/// contiguous buckets keyed by the field
+/// `(v >> bit_offset) & (2^bit_count - 1)` — one radix
+/// pass, not a sort. Bucket `b` occupies
+/// `offsets[b] .. offsets[b + 1]` in the rearranged slice
+/// (with `offsets[2^bit_count] == slice.len()` as the
+/// final fence), so the returned `Vec` has
+/// `2^bit_count + 1` entries. Elements within a bucket
+/// share the bit-range value but are otherwise in
+/// arbitrary order; recursing on each bucket with the next
+/// bit range down gives a radix sort, or the buckets can
+/// be handed off wholesale to a GPU pass. Panics if the
+/// bit range does not fit in 32 bits or `bit_count` is
+/// zero.
+///
+/// # Examples
+///
+/// ```
+/// let mut a = [0b1101, 0b0010, 0b1001, 0b0110];
+/// // Bucket on the top two bits of the low nibble.
+/// let offsets = quicksort::quicksort_bucket_by_bits(&mut a, 2, 2);
+/// assert_eq!(offsets, [0, 1, 2, 3, 4]);
+/// assert_eq!(a, [0b0010, 0b0110, 0b1001, 0b1101]);
+/// ```
+#[cfg(feature = "std")]
+pub fn quicksort_bucket_by_bits(
+    slice: &mut [u32],
+    bit_offset: u32,
+    bit_count: u32,
+) -> Vec<usize> {
+    assert!(
+        bit_count > 0 && bit_offset + bit_count <= 32,
+        "quicksort_bucket_by_bits: bad bit range",
+    );
+    let nbuckets = 1usize << bit_count;
+    let field = |v: u32| ((v >> bit_offset) as usize) & (nbuckets - 1);
+
+    // Count one pass, then prefix-sum into offsets.
+    let mut counts = vec![0; nbuckets];
+    for &v in slice.iter() {
+        counts[field(v)] += 1
+    }
+    let mut offsets = Vec::with_capacity(nbuckets + 1);
+    let mut total = 0;
+    offsets.push(0);
+    for &count in &counts {
+        total += count;
+        offsets.push(total)
+    }
+
+    // Scatter into place cycle by cycle: `cursor[b]` is
+    // the next unfilled slot of bucket b.
+    let mut cursor = offsets.clone();
+    for b in 0..nbuckets {
+        while cursor[b] < offsets[b + 1] {
+            let target = field(slice[cursor[b]]);
+            if target == b {
+                // Already home: this slot is done.
+                cursor[b] += 1
+            } else {
+                let to = cursor[target];
+                slice.swap(cursor[b], to);
+                cursor[target] += 1
+            }
+        }
+    }
+    offsets
+}
+
+#[test]
+fn quicksort_bucket_by_bits_boundaries() {
+    use rand::Rng;
+    let mut a = Vec::with_capacity(500);
+    for _ in 0..500 {
+        a.push(rand::thread_rng().gen_range(0, 1 << 16) as u32)
+    }
+    let sorted_fields = {
+        let mut f: Vec<u32> = a.iter().map(|v| (v >> 4) & 0x7).collect();
+        f.sort();
+        f
+    };
+    let offsets = quicksort_bucket_by_bits(&mut a, 4, 3);
+    assert_eq!(offsets.len(), 9);
+    assert_eq!(offsets[0], 0);
+    assert_eq!(offsets[8], a.len());
+    for b in 0..8 {
+        for v in &a[offsets[b] .. offsets[b + 1]] {
+            // Everything in bucket b shares field value b.
+            assert_eq!((v >> 4) & 0x7, b as u32)
+        }
+    }
+    // The bucketed fields are exactly the input fields.
+    let fields: Vec<u32> = a.iter().map(|v| (v >> 4) & 0x7).collect();
+    assert_eq!(fields, sorted_fields)
+}
+
+/// How well `partition()` centered its pivots over a batch
+/// of inputs, from `partition_centering_stats()`. Offsets
+/// are distances of the pivot index from the slice
+/// midpoint, as a fraction of the half-length: 0.0 is a
+/// perfectly centered pivot, 1.0 one parked at either end.
+#[derive(Clone, Copy, Debug)]
+#[cfg(feature = "std")]
+pub struct CenteringStats {
+    /// Mean offset fraction across the batch.
+    pub mean_offset: f64,
+    /// Largest offset fraction seen.
+    pub worst_offset: f64,
+}
+
+/// Runs `partition()` on a scratch copy of each input and
+/// measures how far each returned pivot index landed from
+/// the middle, so the docs' claim that the pivot ends up
+/// "reasonably close to the middle" can be checked
+/// empirically against your own data distribution rather
+/// than taken on faith. Inputs shorter than two elements
+/// cannot be partitioned and are skipped. Panics if no
+/// input is long enough to measure.
+#[cfg(feature = "std")]
+pub fn partition_centering_stats<T: Ord + Clone>(
+    inputs: &[Vec<T>],
+) -> CenteringStats {
+    let mut total = 0.0;
+    let mut worst = 0.0f64;
+    let mut measured = 0;
+    for input in inputs {
+        let nslice = input.len();
+        if nslice < 2 {
+            continue
+        }
+        let mut scratch = input.clone();
+        let pivot = partition(&mut scratch).unwrap();
+
+        // Offset from the midpoint as a fraction of the
+        // half-length.
+        let midpoint = (nslice - 1) as f64 / 2.0;
+        let offset = (pivot as f64 - midpoint).abs() / (nslice as f64 / 2.0);
+        total += offset;
+        worst = worst.max(offset);
+        measured += 1
+    }
+    assert!(measured > 0, "partition centering of nothing");
+    CenteringStats {
+        mean_offset: total / measured as f64,
+        worst_offset: worst,
+    }
+}
+
+#[test]
+fn partition_centering_random_inputs() {
+    use rand::Rng;
+    let mut inputs = Vec::with_capacity(100);
+    for _ in 0..100 {
+        let n = rand::thread_rng().gen_range(50, 500);
+        let mut a = Vec::with_capacity(n);
+        for _ in 0..n {
+            a.push(rand::thread_rng().gen_range(-1000i64, 1000))
+        }
+        inputs.push(a)
+    }
+    let stats = partition_centering_stats(&inputs);
+    // On random data the pivot should land near the middle
+    // on average, and never outside the slice.
+    assert!(stats.mean_offset < 0.4, "mean offset {}", stats.mean_offset);
+    assert!(stats.worst_offset <= 1.0)
+}
+
+/// Where `quicksort_sticky()` should force an element,
+/// regardless of how it compares.
+#[cfg(feature = "std")]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Sticky {
+    /// Not sticky: sorts normally.
+    None,
+    /// Forced to the front of the slice.
+    First,
+    /// Forced to the back of the slice.
+    Last,
+}
+
+/// Sorts the slice, except that elements flagged
+/// `Sticky::First` are forced to the front and
+/// `Sticky::Last` to the back, regardless of value; the
+/// unflagged middle sorts normally. When several elements
+/// share a sticky flag they sort among themselves by
+/// value, so the front and back blocks are each in
+/// ascending order too. `sticky` is consulted on every
+/// comparison and must be consistent per element.
+///
+/// # Examples
+///
+/// ```
+/// let mut a = [5, 1, 9, 3, 7];
+/// // Pin 9 to the front and 1 to the back.
+/// quicksort::quicksort_sticky(&mut a, |&v| match v {
+///     9 => quicksort::Sticky::First,
+///     1 => quicksort::Sticky::Last,
+///     _ => quicksort::Sticky::None,
+/// });
+/// assert_eq!(a, [9, 3, 5, 7, 1]);
+/// ```
+#[cfg(feature = "std")]
+pub fn quicksort_sticky<T: Ord>(slice: &mut [T], sticky: impl Fn(&T) -> Sticky) {
+    // Band rank: sticky-first, then normal, then
+    // sticky-last.
+    let band = |v: &T| match sticky(v) {
+        Sticky::First => 0,
+        Sticky::None => 1,
+        Sticky::Last => 2,
+    };
+    quicksort_by(slice, |a, b| {
+        band(a).cmp(&band(b)).then_with(|| a.cmp(b))
+    })
+}
+
+#[test]
+fn quicksort_sticky_bands() {
+    let mut a = [4, 8, 2, 9, 1, 7, 3];
+    // Evens stick first, anything above 8 sticks last.
+    quicksort_sticky(&mut a, |&v| {
+        if v % 2 == 0 {
+            Sticky::First
+        } else if v > 8 {
+            Sticky::Last
+        } else {
+            Sticky::None
+        }
+    });
+    assert_eq!(a, [2, 4, 8, 1, 3, 7, 9])
+}
+
+/// Sorts a slice of mutable references by the values they
+/// point to: only the references move, never the
+/// referents. Handy when the backing data is too big to
+/// shuffle or can't be moved at all, but ordered access
+/// through a reference array is wanted.
+pub fn quicksort_refs<T: Ord>(slice: &mut [&mut T]) {
+    // `&mut T` orders by its referent, so the ordinary
+    // sort does the right thing — it just swaps pointers.
+    quicksort(slice)
+}
+
+#[test]
+fn quicksort_refs_orders_referents() {
+    let mut backing = [5, 1, 4, 2, 3];
+    {
+        let mut refs: Vec<&mut i32> = backing.iter_mut().collect();
+        quicksort_refs(&mut refs);
+        let values: Vec<i32> = refs.iter().map(|r| **r).collect();
+        assert_eq!(values, [1, 2, 3, 4, 5]);
+    }
+    // The backing array itself never moved.
+    assert_eq!(backing, [5, 1, 4, 2, 3])
+}
+
+/// Sorts `f64`s by `f64::total_cmp()`, which extends the
+/// usual numeric order to a total order: `-NaN` sorts
+/// before `-∞`, `+NaN` after `+∞`, and `-0.0` before
+/// `0.0`. Every input — NaNs, signed zeros, infinities —
+/// gets one deterministic position; nothing panics.
+///
+/// # Examples
+///
+/// ```
+/// let mut a = [1.0, f64::NAN, -0.0, 0.0, f64::NEG_INFINITY, f64::INFINITY];
+/// quicksort::quicksort_floats(&mut a);
+/// assert!(a[5].is_nan());
+/// assert_eq!(a[.. 5], [f64::NEG_INFINITY, -0.0, 0.0, 1.0, f64::INFINITY]);
+/// assert!(a[1].is_sign_negative() && a[2].is_sign_positive());
+/// ```
+#[cfg(feature = "std")]
+pub fn quicksort_floats(slice: &mut [f64]) {
+    quicksort_by(slice, f64::total_cmp)
+}
+
+/// `f32` counterpart of `quicksort_floats()`, with the
+/// same `total_cmp` ordering.
+#[cfg(feature = "std")]
+pub fn quicksort_floats_f32(slice: &mut [f32]) {
+    quicksort_by(slice, f32::total_cmp)
+}
+
+#[test]
+fn quicksort_floats_edge_cases() {
+    let mut a = [
+        1.0,
+        f64::NAN,
+        -0.0,
+        0.0,
+        f64::NEG_INFINITY,
+        f64::INFINITY,
+        -f64::NAN,
+    ];
+    quicksort_floats(&mut a);
+    assert!(a[0].is_nan() && a[0].is_sign_negative());
+    assert_eq!(a[1], f64::NEG_INFINITY);
+    assert!(a[2] == 0.0 && a[2].is_sign_negative());
+    assert!(a[3] == 0.0 && a[3].is_sign_positive());
+    assert_eq!(a[4], 1.0);
+    assert_eq!(a[5], f64::INFINITY);
+    assert!(a[6].is_nan() && a[6].is_sign_positive());
+
+    let mut a = [2.0f32, f32::NAN, -1.0];
+    quicksort_floats_f32(&mut a);
+    assert_eq!(a[.. 2], [-1.0, 2.0]);
+    assert!(a[2].is_nan())
+}
+
+/// Sorts the slice in place and returns `(value, count)`
+/// pairs for every distinct value, in ascending order —
+/// value counts without needing `Hash`, since after
+/// sorting each distinct value occupies one contiguous
+/// run. An empty slice yields no pairs; an all-equal
+/// slice yields exactly one.
+///
+/// # Examples
+///
+/// ```
+/// let mut a = [3, 1, 3, 2, 1, 3];
+/// let counts = quicksort::quicksort_frequencies(&mut a);
+/// assert_eq!(counts, [(1, 2), (2, 1), (3, 3)]);
+/// ```
+#[cfg(feature = "std")]
+pub fn quicksort_frequencies<T: Ord + Clone>(slice: &mut [T]) -> Vec<(T, usize)> {
+    quicksort(slice);
+
+    let mut counts: Vec<(T, usize)> = Vec::new();
+    for v in slice.iter() {
+        match counts.last_mut() {
+            Some(last) if last.0 == *v => last.1 += 1,
+            _ => counts.push((v.clone(), 1)),
+        }
+    }
+    counts
+}
+
+#[test]
+fn quicksort_frequencies_counts() {
+    let mut a = [3, 1, 3, 2, 1, 3];
+    assert_eq!(quicksort_frequencies(&mut a), [(1, 2), (2, 1), (3, 3)]);
+    assert_eq!(a, [1, 1, 2, 3, 3, 3]);
+
+    let mut empty: [u32; 0] = [];
+    assert_eq!(quicksort_frequencies(&mut empty), []);
+
+    let mut same = [9; 40];
+    assert_eq!(quicksort_frequencies(&mut same), [(9, 40)])
+}
+
+#[test]
+fn partition_short_slices() {
+    let mut empty: [u32; 0] = [];
+    assert_eq!(partition(&mut empty), None);
+    assert_eq!(partition(&mut [7]), None);
+    assert!(partition(&mut [2, 1]).is_some())
+}
+
+/// Declarative multi-key sort specification, in the style
+/// of SQL's `ORDER BY`: chain key extractors, each with
+/// its own direction, then apply the whole spec at once.
+/// Keys are compared in the order they were added, each
+/// consulted only when all earlier keys tie.
+///
+/// # Examples
+///
+/// ```
+/// let mut rows = [(1, "b"), (2, "a"), (1, "a")];
+/// // ORDER BY .0 ASC, .1 DESC
+/// quicksort::SortSpec::new()
+///     .by_key_asc(|r: &(i32, &str)| r.0)
+///     .by_key_desc(|r: &(i32, &str)| r.1)
+///     .sort(&mut rows);
+/// assert_eq!(rows, [(1, "b"), (1, "a"), (2, "a")]);
+/// ```
+#[cfg(feature = "std")]
+pub struct SortSpec<T> {
+    keys: Vec<KeyCompare<T>>,
+}
+
+// A boxed key comparator as stored by `SortSpec`.
+#[cfg(feature = "std")]
+type KeyCompare<T> = Box<dyn Fn(&T, &T) -> Ordering>;
+
+#[cfg(feature = "std")]
+impl<T> SortSpec<T> {
+    /// Makes an empty spec; with no keys added, `sort()`
+    /// leaves its input untouched.
+    pub fn new() -> SortSpec<T> {
+        SortSpec { keys: Vec::new() }
+    }
+
+    /// Adds the next key, ascending.
+    pub fn by_key_asc<K: Ord, F: Fn(&T) -> K + 'static>(
+        mut self,
+        f: F,
+    ) -> SortSpec<T> {
+        self.keys.push(Box::new(move |a, b| f(a).cmp(&f(b))));
+        self
+    }
+
+    /// Adds the next key, descending.
+    pub fn by_key_desc<K: Ord, F: Fn(&T) -> K + 'static>(
+        mut self,
+        f: F,
+    ) -> SortSpec<T> {
+        self.keys.push(Box::new(move |a, b| f(b).cmp(&f(a))));
+        self
+    }
+
+    /// Sorts the slice by the accumulated keys.
+    pub fn sort(&self, slice: &mut [T]) {
+        quicksort_by(slice, |a, b| {
+            for key in &self.keys {
+                // First decisive key wins.
+                let ord = key(a, b);
+                if ord != Ordering::Equal {
+                    return ord
+                }
+            }
+            Ordering::Equal
+        })
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T> Default for SortSpec<T> {
+    fn default() -> SortSpec<T> {
+        SortSpec::new()
+    }
+}
+
+#[test]
+fn sort_spec_composite_order() {
+    struct Rec {
+        dept: u32,
+        score: i64,
+    }
+    let mut recs = vec![
+        Rec { dept: 2, score: 10 },
+        Rec { dept: 1, score: 5 },
+        Rec { dept: 2, score: 30 },
+        Rec { dept: 1, score: 20 },
+    ];
+    // Ascending by department, descending by score.
+    SortSpec::new()
+        .by_key_asc(|r: &Rec| r.dept)
+        .by_key_desc(|r: &Rec| r.score)
+        .sort(&mut recs);
+    let order: Vec<(u32, i64)> =
+        recs.iter().map(|r| (r.dept, r.score)).collect();
+    assert_eq!(order, [(1, 20), (1, 5), (2, 30), (2, 10)])
+}
+
+/// Returns true iff every adjacent pair of the slice is in
+/// non-decreasing order, short-circuiting at the first
+/// violation. Empty and single-element slices are
+/// trivially sorted. `quicksort()` uses this in a debug
+/// assertion on its own output, so partition regressions
+/// surface immediately in debug builds.
+///
+/// # Examples
+///
+/// ```
+/// assert!(quicksort::is_sorted(&[1, 2, 2, 3]));
+/// assert!(!quicksort::is_sorted(&[2, 1]));
+/// assert!(quicksort::is_sorted::<u32>(&[]));
+/// ```
+pub fn is_sorted<T: Ord>(slice: &[T]) -> bool {
+    is_sorted_by(slice, |a, b| a.cmp(b))
+}
+
+/// Comparator version of `is_sorted()`: true iff no
+/// adjacent pair comes back `Greater` from `compare`.
+pub fn is_sorted_by<T, F: FnMut(&T, &T) -> Ordering>(
+    slice: &[T],
+    mut compare: F,
+) -> bool {
+    slice
+        .windows(2)
+        .all(|w| compare(&w[0], &w[1]) != Ordering::Greater)
+}
+
+#[test]
+fn is_sorted_cases() {
+    assert!(is_sorted::<u32>(&[]));
+    assert!(is_sorted(&[5]));
+    assert!(is_sorted(&[1, 2, 3]));
+    assert!(is_sorted(&[4, 4, 4]));
+    assert!(!is_sorted(&[1, 3, 2]));
+    assert!(is_sorted_by(&[3, 2, 1], |a, b| b.cmp(a)))
+}
+
+/// Re-sorts a slice that was fully sorted before a bulk
+/// update overwrote the contiguous `dirty` range.
+/// Precondition: the portions before and after `dirty` are
+/// untouched since the last full sort, so they are each
+/// still sorted *and* everything in the prefix is no
+/// larger than everything in the suffix. Only the dirty
+/// range is sorted from scratch; it is then merged with
+/// the clean remainder in one linear pass, for
+/// O(d log d + n) work on an n-element slice with a
+/// d-element dirty range, instead of a full re-sort.
+/// Panics if `dirty` is out of bounds.
+///
+/// # Examples
+///
+/// ```
+/// let mut a = [1, 3, 5, 90, 20, 11, 13];
+/// // Positions 3..5 were bulk-updated; the rest is clean.
+/// quicksort::resort_range(&mut a, 3..5);
+/// assert_eq!(a, [1, 3, 5, 11, 13, 20, 90]);
+/// ```
+#[cfg(feature = "std")]
+pub fn resort_range<T: Ord + Clone>(
+    slice: &mut [T],
+    dirty: std::ops::Range<usize>,
+) {
+    assert!(
+        dirty.start <= dirty.end && dirty.end <= slice.len(),
+        "resort_range: dirty range out of bounds",
+    );
+    quicksort(&mut slice[dirty.clone()]);
+
+    // The prefix and suffix together form one sorted run;
+    // merge the freshly sorted dirty run with it.
+    let mut clean: Vec<T> = slice[.. dirty.start]
+        .iter()
+        .chain(slice[dirty.end ..].iter())
+        .cloned()
+        .collect();
+    let dirty_run: Vec<T> = slice[dirty].to_vec();
+    let mut i = 0;
+    let mut j = 0;
+    for out in slice.iter_mut() {
+        if j >= dirty_run.len()
+            || (i < clean.len() && clean[i] <= dirty_run[j]) {
+            std::mem::swap(out, &mut clean[i]);
+            i += 1
+        } else {
+            *out = dirty_run[j].clone();
+            j += 1
+        }
+    }
+}
+
+#[test]
+fn resort_range_middle_update() {
+    let mut a: Vec<i64> = (0..100).map(|i| i * 3).collect();
+    // Bulk-overwrite the middle with out-of-order values.
+    for (k, i) in (40..60).enumerate() {
+        a[i] = 500 - 7 * k as i64
+    }
+    let mut expected = a.clone();
+    expected.sort();
+    resort_range(&mut a, 40..60);
+    assert_eq!(a, expected);
+
+    // Empty dirty range: already sorted stays sorted.
+    let mut b = vec![1, 2, 3];
+    resort_range(&mut b, 1..1);
+    assert_eq!(b, [1, 2, 3])
+}
+
+/// Partitions the slice around the element the caller
+/// picked: `slice[pivot_idx]` is taken as the pivot value,
+/// everything no larger is moved before it, everything
+/// larger after, and the pivot element's final resting
+/// index is returned — the returned position holds the
+/// very element that started at `pivot_idx`. For guided
+/// selection where the caller already knows a good pivot,
+/// bypassing `partition()`'s own pivot heuristic. Panics
+/// if `pivot_idx` is out of bounds.
+///
+/// # Examples
+///
+/// ```
+/// let mut a = [5, 1, 4, 2, 3];
+/// let at = quicksort::partition_at(&mut a, 4);
+/// assert_eq!(a[at], 3);
+/// assert!(a[.. at].iter().all(|&v| v <= 3));
+/// assert!(a[at ..].iter().all(|&v| v >= 3));
+/// ```
+pub fn partition_at<T: Ord>(slice: &mut [T], pivot_idx: usize) -> usize {
+    assert!(
+        pivot_idx < slice.len(),
+        "partition_at: pivot index out of bounds",
+    );
+    partition_around(slice, pivot_idx)
+}
+
+#[test]
+fn partition_at_random_pivots() {
+    use rand::Rng;
+    for _ in 0..50 {
+        let n = rand::thread_rng().gen_range(1, 60);
+        let mut a: Vec<i64> = Vec::with_capacity(n);
+        for _ in 0..n {
+            a.push(rand::thread_rng().gen_range(-30, 30))
+        }
+        let pivot_idx = rand::thread_rng().gen_range(0, n);
+        let pivot_val = a[pivot_idx];
+        let at = partition_at(&mut a, pivot_idx);
+        // The chosen value ended up at the returned index,
+        // with the partition property around it.
+        assert_eq!(a[at], pivot_val);
+        assert!(a[.. at].iter().all(|&v| v <= pivot_val));
+        assert!(a[at ..].iter().all(|&v| v >= pivot_val))
+    }
+}
+
+/// Computes the permutation that would sort the slice —
+/// without touching it — decomposed into disjoint cycles
+/// of indices. Rotating each cycle (move the element at
+/// the first index to the second, the second to the third,
+/// and the last back to the first slot) sorts the data
+/// with every element moved at most once, which is how the
+/// indirect sort applies its permutation; exposing the
+/// cycles lets callers reorder parallel arrays the same
+/// minimal-move way. Fixed points (elements already in
+/// place) are omitted, so an already-sorted slice yields
+/// no cycles. Ties are broken by original index, keeping
+/// the permutation deterministic.
+#[cfg(feature = "std")]
+pub fn sort_permutation_cycles<T: Ord>(slice: &[T]) -> Vec<Vec<usize>> {
+    let nslice = slice.len();
+
+    // Where each original index should end up.
+    let mut order: Vec<usize> = (0..nslice).collect();
+    quicksort_by(&mut order, |&i, &j| {
+        slice[i].cmp(&slice[j]).then_with(|| i.cmp(&j))
+    });
+    let mut dest = vec![0; nslice];
+    for (target, &source) in order.iter().enumerate() {
+        dest[source] = target
+    }
+
+    // Trace each cycle once, from its smallest index.
+    let mut cycles = Vec::new();
+    let mut seen = vec![false; nslice];
+    for start in 0..nslice {
+        if seen[start] || dest[start] == start {
+            continue
+        }
+        let mut cycle = Vec::new();
+        let mut i = start;
+        while !seen[i] {
+            seen[i] = true;
+            cycle.push(i);
+            i = dest[i]
+        }
+        cycles.push(cycle)
+    }
+    cycles
+}
+
+#[test]
+fn sort_permutation_cycles_apply() {
+    let a = [40, 10, 30, 20, 10];
+    let cycles = sort_permutation_cycles(&a);
+
+    // Rotate each cycle: every element moves at most once.
+    let mut b = a;
+    let mut moves = 0;
+    for cycle in &cycles {
+        let held = b[cycle[cycle.len() - 1]];
+        for k in (1..cycle.len()).rev() {
+            b[cycle[k]] = b[cycle[k - 1]];
+            moves += 1
+        }
+        b[cycle[0]] = held;
+        moves += 1
+    }
+    let mut expected = a;
+    expected.sort();
+    assert_eq!(b, expected);
+    assert!(moves <= a.len());
+
+    // Sorted input: nothing moves.
+    assert!(sort_permutation_cycles(&[1, 2, 3]).is_empty())
+}
+
+/// Sorts the slice largest-first, by handing
+/// `quicksort_by()` the reversed comparator — no partition
+/// logic of its own. Unstable like the ascending sort:
+/// equal elements land in no particular order.
+///
+/// # Examples
+///
+/// ```
+/// let mut a = [3, 1, 2];
+/// quicksort::quicksort_desc(&mut a);
+/// assert_eq!(a, [3, 2, 1]);
+/// ```
+#[cfg(feature = "std")]
+pub fn quicksort_desc<T: Ord>(slice: &mut [T]) {
+    quicksort_by(slice, |a, b| b.cmp(a))
+}
+
+#[test]
+fn quicksort_desc_reverses_ascending() {
+    use rand::Rng;
+    let mut a = Vec::with_capacity(300);
+    for _ in 0..300 {
+        a.push(rand::thread_rng().gen_range(-100i64, 100))
+    }
+    let mut expected = a.clone();
+    quicksort(&mut expected);
+    expected.reverse();
+    quicksort_desc(&mut a);
+    assert_eq!(a, expected);
+
+    let mut empty: [u32; 0] = [];
+    quicksort_desc(&mut empty);
+    quicksort_desc(&mut [1])
+}
+
+/// Sorts by a float key limited to `decimals` decimal
+/// places, so keys differing only beyond that precision
+/// compare equal and group together — binning and sorting
+/// in one step. The limiting mode is truncation toward
+/// zero: the key is scaled by `10^decimals` and the
+/// fractional remainder dropped, so `1.234` and `1.239`
+/// share the bin `1.23` at two decimals but split at
+/// three. Truncated keys are compared with `total_cmp`,
+/// so NaN keys order deterministically (after everything
+/// else) rather than panicking.
+#[cfg(feature = "std")]
+pub fn quicksort_by_rounded_float<T>(
+    slice: &mut [T],
+    key: impl Fn(&T) -> f64,
+    decimals: u32,
+) {
+    let factor = 10f64.powi(decimals as i32);
+    let binned = |v: &T| (key(v) * factor).trunc();
+    quicksort_by(slice, |a, b| binned(a).total_cmp(&binned(b)))
+}
+
+#[test]
+fn quicksort_by_rounded_float_bins() {
+    // At two decimals 1.234 and 1.239 tie, so the pair
+    // keeps whatever order the sort gives it, but both
+    // land between the outer keys.
+    let mut a = [(1.239, 'b'), (0.5, 'x'), (1.234, 'a'), (2.0, 'y')];
+    quicksort_by_rounded_float(&mut a, |p| p.0, 2);
+    assert_eq!(a[0].1, 'x');
+    assert_eq!(a[3].1, 'y');
+
+    // At three decimals they separate: 1.234 < 1.239.
+    let mut a = [(1.239, 'b'), (1.234, 'a')];
+    quicksort_by_rounded_float(&mut a, |p| p.0, 3);
+    assert_eq!(a[0].1, 'a');
+    assert_eq!(a[1].1, 'b')
+}
+
+#[test]
+fn partition_survives_inconsistent_comparator() {
+    use rand::Rng;
+    // Rock-paper-scissors on value mod 3: deliberately
+    // non-transitive. The partition result is meaningless,
+    // but the call must terminate with an in-range pivot —
+    // checked through the unvalidating path the release
+    // build runs.
+    let mut beats = |a: &u32, b: &u32| {
+        match (a % 3, b % 3) {
+            (x, y) if x == y => Ordering::Equal,
+            (0, 1) | (1, 2) | (2, 0) => Ordering::Less,
+            _ => Ordering::Greater,
+        }
+    };
+    for _ in 0..20 {
+        let n = rand::thread_rng().gen_range(2, 500);
+        let mut a: Vec<u32> = Vec::with_capacity(n);
+        for _ in 0..n {
+            a.push(rand::thread_rng().gen_range(0, 1000) as u32)
+        }
+        let pivot = partition_by_checked(&mut a, &mut beats, false);
+        assert!(pivot < n)
+    }
+}
+
+/// Sorts the slice normally except that every element the
+/// predicate flags is forced to the end — the
+/// "Other"/"Uncategorized" bucket UI lists keep last. The
+/// flagged tail is itself sorted by value, as is the
+/// unflagged front. A focused version of
+/// `quicksort_sticky()` for the one common case.
+///
+/// # Examples
+///
+/// ```
+/// let mut a = [4, 0, 3, 0, 1];
+/// // Zeros mean "uncategorized": keep them last.
+/// quicksort::quicksort_variant_last(&mut a, |&v| v == 0);
+/// assert_eq!(a, [1, 3, 4, 0, 0]);
+/// ```
+#[cfg(feature = "std")]
+pub fn quicksort_variant_last<T: Ord, F: Fn(&T) -> bool>(
+    slice: &mut [T],
+    is_last_variant: F,
+) {
+    quicksort_by(slice, |a, b| {
+        is_last_variant(a)
+            .cmp(&is_last_variant(b))
+            .then_with(|| a.cmp(b))
+    })
+}
+
+#[test]
+fn quicksort_variant_last_tail() {
+    let mut a = [7, 2, 9, 5, 2, 8, 4];
+    // Evens are the "other" bucket.
+    quicksort_variant_last(&mut a, |&v| v % 2 == 0);
+    assert_eq!(a, [5, 7, 9, 2, 2, 4, 8])
+}
+
+/// Sorts the slice unless the wall clock passes
+/// `deadline` first. The clock is consulted once per
+/// partition pass — cheap next to the pass itself, so
+/// overruns are bounded by roughly one partition of the
+/// largest remaining subrange plus one insertion-sorted
+/// remnant. On timeout the sort stops and returns
+/// `false`, leaving a valid permutation of the input
+/// (partially sorted, every partition completed so far
+/// intact); completing in time returns `true`.
+#[cfg(feature = "std")]
+pub fn quicksort_deadline<T: Ord>(
+    slice: &mut [T],
+    deadline: std::time::Instant,
+) -> bool {
+    let mut stack: Vec<(usize, usize)> = Vec::new();
+    let mut lo = 0;
+    let mut hi = slice.len();
+    loop {
+        while hi - lo > INSERTION_THRESHOLD {
+            // One clock check per partition.
+            if std::time::Instant::now() >= deadline {
+                return false
+            }
+            let (lt_end, gt_start) = partition_three_way(&mut slice[lo .. hi]);
+            let (lt_end, gt_start) = (lo + lt_end, lo + gt_start);
+            if lt_end - lo < hi - gt_start {
+                stack.push((lo, lt_end));
+                lo = gt_start
+            } else {
+                stack.push((gt_start, hi));
+                hi = lt_end
+            }
+        }
+        insertion_sort(&mut slice[lo .. hi]);
+        match stack.pop() {
+            Some((l, h)) => {
+                lo = l;
+                hi = h
+            }
+            None => return true,
+        }
+    }
+}
+
+#[test]
+fn quicksort_deadline_expiry() {
+    use rand::Rng;
+    use std::time::{Duration, Instant};
+
+    let mut a = Vec::with_capacity(2000);
+    for _ in 0..2000 {
+        a.push(rand::thread_rng().gen_range(-1000i64, 1000))
+    }
+    let mut expected = a.clone();
+    expected.sort();
+
+    // Already-expired deadline: aborts, but the slice is
+    // still a permutation of the input.
+    let mut b = a.clone();
+    assert!(!quicksort_deadline(&mut b, Instant::now() - Duration::from_secs(1)));
+    let mut b_sorted = b.clone();
+    b_sorted.sort();
+    assert_eq!(b_sorted, expected);
+
+    // Generous deadline: completes, fully sorted.
+    assert!(quicksort_deadline(&mut a, Instant::now() + Duration::from_secs(60)));
+    assert_eq!(a, expected)
+}
+
+/// Sorts the slice in place and returns the starting index
+/// of each distinct value's run, in ascending value order:
+/// the i-th distinct value occupies
+/// `index[i] .. index.get(i + 1)` (end of slice for the
+/// last). The compact companion of
+/// `quicksort_group_ranges()` — just the start offsets, as
+/// binary search over groups only needs those.
+///
+/// # Examples
+///
+/// ```
+/// let mut a = [2, 1, 2, 2];
+/// let index = quicksort::quicksort_group_index(&mut a);
+/// assert_eq!(index, [0, 1]);
+/// ```
+#[cfg(feature = "std")]
+pub fn quicksort_group_index<T: Ord>(slice: &mut [T]) -> Vec<usize> {
+    quicksort(slice);
+
+    let mut index = Vec::new();
+    for i in 0..slice.len() {
+        if i == 0 || slice[i - 1] != slice[i] {
+            // A new distinct value's run starts here.
+            index.push(i)
+        }
+    }
+    index
+}
+
+#[test]
+fn quicksort_group_index_runs() {
+    let mut a = [5, 3, 5, 1, 3, 3, 1];
+    let index = quicksort_group_index(&mut a);
+    assert_eq!(a, [1, 1, 3, 3, 3, 5, 5]);
+    assert_eq!(index, [0, 2, 5]);
+    // Each offset starts a run of one distinct value.
+    for (k, &start) in index.iter().enumerate() {
+        let end = index.get(k + 1).cloned().unwrap_or(a.len());
+        assert!(a[start .. end].iter().all(|&v| v == a[start]));
+        if start > 0 {
+            assert!(a[start - 1] < a[start])
+        }
+    }
+
+    let mut empty: [u32; 0] = [];
+    assert_eq!(quicksort_group_index(&mut empty), [])
+}
+
+/// Sorts tiny sets whose comparisons are enormously
+/// expensive — perceptual image diffs, say — guaranteeing
+/// no pair of elements is ever compared twice. `compare`
+/// is called with *original* index pairs, `(i, j)` with
+/// `i < j`, and every result is cached by pair; the sort
+/// runs over an index permutation so positions never
+/// shift under the cache. At most `n(n-1)/2` comparisons
+/// happen across the whole sort, at a memory cost of one
+/// map entry per distinct pair actually compared (up to
+/// O(n²) — intended for n up to around 50).
+#[cfg(feature = "std")]
+pub fn quicksort_cached_pairwise<T, F: FnMut(usize, usize) -> Ordering>(
+    slice: &mut [T],
+    mut compare: F,
+) {
+    use std::collections::HashMap;
+
+    let nslice = slice.len();
+    let mut cache: HashMap<(usize, usize), Ordering> = HashMap::new();
+
+    let mut perm: Vec<usize> = (0..nslice).collect();
+    quicksort_by(&mut perm, |&i, &j| {
+        if i == j {
+            return Ordering::Equal
+        }
+        let pair = (i.min(j), i.max(j));
+        let ord = *cache
+            .entry(pair)
+            .or_insert_with(|| compare(pair.0, pair.1));
+        if i < j { ord } else { ord.reverse() }
+    });
+
+    // Apply the permutation with cycle-following swaps.
+    let mut dest = vec![0; nslice];
+    for (target, &source) in perm.iter().enumerate() {
+        dest[source] = target
+    }
+    for i in 0..nslice {
+        while dest[i] != i {
+            let j = dest[i];
+            slice.swap(i, j);
+            dest.swap(i, j)
+        }
+    }
+}
+
+#[test]
+fn quicksort_cached_pairwise_no_repeats() {
+    use std::collections::HashSet;
+
+    let original = [9i32, 4, 7, 1, 8, 2, 6, 3, 5, 0, 11, 10];
+    let mut a = original;
+    let mut pairs_seen: HashSet<(usize, usize)> = HashSet::new();
+    quicksort_cached_pairwise(&mut a, |i, j| {
+        // Every queried pair must be new.
+        assert!(pairs_seen.insert((i, j)), "pair ({}, {}) repeated", i, j);
+        original[i].cmp(&original[j])
+    });
+    assert_eq!(a, [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11]);
+    let n = original.len();
+    assert!(pairs_seen.len() <= n * (n - 1) / 2)
+}
+
+/// Alternative partition that scans strictly inward from
+/// both ends — the classic Hoare sweep — swapping
+/// out-of-place pairs as the two cursors meet. Both
+/// cursors move through memory linearly, exactly the
+/// access pattern hardware prefetchers are built for, so
+/// on large arrays this trades `partition()`'s
+/// balance-centering bookkeeping for raw locality.
+/// (Explicit prefetch intrinsics are not stable Rust;
+/// strictly-linear scans let the hardware do it.) The
+/// postcondition is invariant-compatible with
+/// `partition()`: the returned index holds the pivot
+/// value, everything before it is `<=` and everything
+/// after `>=`. The pivot is chosen median-of-three.
+/// Panics on slices of fewer than two elements.
+pub fn partition_bidirectional<T: Ord>(slice: &mut [T]) -> usize {
+    let nslice = slice.len();
+    if nslice < 2 {
+        panic!("partition of short slice")
+    }
+    let last = nslice - 1;
+
+    // Median-of-three, parked at the end out of scan
+    // range.
+    if nslice >= 3 {
+        let mid = nslice / 2;
+        if slice[0] > slice[mid] {
+            slice.swap(0, mid)
+        }
+        if slice[mid] > slice[last] {
+            slice.swap(mid, last)
+        }
+        if slice[0] > slice[mid] {
+            slice.swap(0, mid)
+        }
+        slice.swap(mid, last)
+    }
+
+    // Sweep inward: i rises over elements <= pivot, j
+    // falls over elements >= pivot, strays get swapped.
+    let mut i = 0;
+    let mut j = last;
+    loop {
+        while i < j && slice[i] <= slice[last] {
+            i += 1
+        }
+        while i < j && slice[j - 1] >= slice[last] {
+            j -= 1
+        }
+        if i >= j {
+            break
+        }
+        slice.swap(i, j - 1);
+        i += 1;
+        j -= 1
+    }
+
+    // Drop the pivot between the halves.
+    slice.swap(i, last);
+    i
+}
+
+#[test]
+fn partition_bidirectional_properties() {
+    use rand::Rng;
+    for _ in 0..50 {
+        let n = rand::thread_rng().gen_range(2, 300);
+        let mut a: Vec<i64> = Vec::with_capacity(n);
+        for _ in 0..n {
+            a.push(rand::thread_rng().gen_range(-40, 40))
+        }
+        let pivot = partition_bidirectional(&mut a);
+        assert!(a[.. pivot].iter().all(|v| *v <= a[pivot]));
+        assert!(a[pivot ..].iter().all(|v| *v >= a[pivot]))
+    }
+}
+
+#[test]
+fn partition_bidirectional_throughput() {
+    use rand::Rng;
+    use std::time::Instant;
+
+    // Informal benchmark; run with `--nocapture` to see
+    // the numbers. (The current partition pays for its
+    // test-build invariant assertions here, so compare
+    // release numbers before drawing conclusions.)
+    let n = 20_000;
+    let mut a: Vec<u64> = Vec::with_capacity(n);
+    for _ in 0..n {
+        a.push(rand::thread_rng().gen_range(0, 1 << 30) as u64)
+    }
+    let bytes = (n * std::mem::size_of::<u64>()) as f64;
+
+    let mut b = a.clone();
+    let start = Instant::now();
+    let pivot = partition_bidirectional(&mut b);
+    let tp_bidi = bytes / start.elapsed().as_secs_f64() / 1e6;
+    assert!(b[.. pivot].iter().all(|v| *v <= b[pivot]));
+
+    let mut b = a.clone();
+    let start = Instant::now();
+    partition(&mut b).unwrap();
+    let tp_current = bytes / start.elapsed().as_secs_f64() / 1e6;
+
+    println!(
+        "partition_bidirectional {:.0} MB/s, partition {:.0} MB/s",
+        tp_bidi, tp_current,
+    )
+}
+
+/// A boxed chainable comparator, as consumed by
+/// `quicksort_comparator_chain()`.
+#[cfg(feature = "std")]
+pub type ChainedCompare<T> = Box<dyn FnMut(&T, &T) -> Ordering>;
+
+/// Sorts with a runtime-assembled chain of fallback
+/// comparators: each comparison tries the chain in order
+/// and the first non-`Equal` verdict wins, so later
+/// comparators only ever break ties the earlier ones
+/// leave. The result ordering therefore depends on the
+/// chain order — reordering the chain reorders the
+/// output. An empty chain compares everything equal and
+/// leaves the slice some permutation of itself.
+#[cfg(feature = "std")]
+pub fn quicksort_comparator_chain<T>(
+    slice: &mut [T],
+    comparators: &mut [ChainedCompare<T>],
+) {
+    quicksort_by(slice, |a, b| {
+        for compare in comparators.iter_mut() {
+            // First decisive comparator wins.
+            let ord = compare(a, b);
+            if ord != Ordering::Equal {
+                return ord
+            }
+        }
+        Ordering::Equal
+    })
+}
+
+#[test]
+fn quicksort_comparator_chain_tiebreak() {
+    let mut a = [(2, 'c'), (1, 'z'), (2, 'a'), (1, 'b')];
+    let mut chain: Vec<ChainedCompare<(i32, char)>> =
+        vec![
+            // Primary: the number. Secondary: the letter.
+            Box::new(|x, y| x.0.cmp(&y.0)),
+            Box::new(|x, y| x.1.cmp(&y.1)),
+        ];
+    quicksort_comparator_chain(&mut a, &mut chain);
+    assert_eq!(a, [(1, 'b'), (1, 'z'), (2, 'a'), (2, 'c')])
+}
+
+/// Sorts a slice of `Rc` pointers by the values they point
+/// to. Only the pointers move — no referent is cloned or
+/// even requires `T: Clone` — so the same shared items can
+/// be kept in several independently sorted views cheaply.
+#[cfg(feature = "std")]
+pub fn quicksort_rc<T: Ord>(slice: &mut [std::rc::Rc<T>]) {
+    quicksort_by(slice, |a, b| a.cmp(b))
+}
+
+#[test]
+fn quicksort_rc_orders_referents() {
+    use std::rc::Rc;
+
+    // No Clone anywhere in sight.
+    struct Opaque(i32);
+    impl PartialEq for Opaque {
+        fn eq(&self, other: &Opaque) -> bool {
+            self.0 == other.0
+        }
+    }
+    impl Eq for Opaque {}
+    impl PartialOrd for Opaque {
+        fn partial_cmp(&self, other: &Opaque) -> Option<Ordering> {
+            Some(self.cmp(other))
+        }
+    }
+    impl Ord for Opaque {
+        fn cmp(&self, other: &Opaque) -> Ordering {
+            self.0.cmp(&other.0)
+        }
+    }
+
+    let shared = Rc::new(Opaque(2));
+    let mut a = vec![
+        Rc::new(Opaque(3)),
+        shared.clone(),
+        Rc::new(Opaque(1)),
+        shared.clone(),
+    ];
+    quicksort_rc(&mut a);
+    let values: Vec<i32> = a.iter().map(|r| r.0).collect();
+    assert_eq!(values, [1, 2, 2, 3]);
+    // The equal entries are still the same shared
+    // allocation.
+    assert!(Rc::ptr_eq(&a[1], &shared) && Rc::ptr_eq(&a[2], &shared))
+}
+
+/// Where `quicksort_nulls()` should place the `None`
+/// entries, mirroring SQL's `NULLS FIRST` / `NULLS LAST`.
+#[cfg(feature = "std")]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum NullOrder {
+    /// All `None` entries before every `Some`.
+    First,
+    /// All `None` entries after every `Some`.
+    Last,
+}
+
+/// Sorts a slice of `Option`s with database NULL
+/// semantics: `Some` values order naturally among
+/// themselves, and every `None` goes entirely first or
+/// entirely last per `nulls` — unlike the derived
+/// `Option` ordering, which always puts `None` before
+/// `Some`.
+///
+/// # Examples
+///
+/// ```
+/// let mut a = [Some(2), None, Some(1), None];
+/// quicksort::quicksort_nulls(&mut a, quicksort::NullOrder::Last);
+/// assert_eq!(a, [Some(1), Some(2), None, None]);
+/// ```
+#[cfg(feature = "std")]
+pub fn quicksort_nulls<T: Ord>(slice: &mut [Option<T>], nulls: NullOrder) {
+    quicksort_by(slice, |a, b| {
+        match (a, b) {
+            (Some(a), Some(b)) => a.cmp(b),
+            (None, None) => Ordering::Equal,
+            (None, Some(_)) => {
+                if nulls == NullOrder::First { Ordering::Less } else { Ordering::Greater }
+            }
+            (Some(_), None) => {
+                if nulls == NullOrder::First { Ordering::Greater } else { Ordering::Less }
+            }
+        }
+    })
+}
+
+#[test]
+fn quicksort_nulls_placement() {
+    let mixed = [Some(3), None, Some(1), Some(2), None];
+
+    let mut a = mixed;
+    quicksort_nulls(&mut a, NullOrder::First);
+    assert_eq!(a, [None, None, Some(1), Some(2), Some(3)]);
+
+    let mut a = mixed;
+    quicksort_nulls(&mut a, NullOrder::Last);
+    assert_eq!(a, [Some(1), Some(2), Some(3), None, None])
+}
+
+/// Sorts the slice with `quicksort_floats()`, discards the
+/// lowest and highest `trim_fraction` of the values
+/// (`floor(trim_fraction * len)` from each end), and
+/// returns the mean of what remains — the classic
+/// outlier-resistant trimmed mean. `trim_fraction` must
+/// lie in `[0, 0.5)` so something always survives the
+/// trim; panics otherwise, or if the slice is empty.
+///
+/// # Examples
+///
+/// ```
+/// let mut a = [1.0, 2.0, 3.0, 1000.0];
+/// // Trim a quarter from each end: the outlier goes.
+/// assert_eq!(quicksort::trimmed_mean(&mut a, 0.25), 2.5);
+/// ```
+#[cfg(feature = "std")]
+pub fn trimmed_mean(slice: &mut [f64], trim_fraction: f64) -> f64 {
+    assert!(
+        (0.0..0.5).contains(&trim_fraction),
+        "trimmed_mean: trim fraction must lie in [0, 0.5)",
+    );
+    assert!(!slice.is_empty(), "trimmed mean of nothing");
+
+    quicksort_floats(slice);
+    let trim = (trim_fraction * slice.len() as f64) as usize;
+    let kept = &slice[trim .. slice.len() - trim];
+    kept.iter().sum::<f64>() / kept.len() as f64
+}
+
+#[test]
+fn trimmed_mean_ignores_outliers() {
+    // Ten ordinary values plus wild outliers either side.
+    let mut a = vec![-1e9, 4.0, 6.0, 5.0, 5.0, 4.0, 6.0, 5.0, 5.0, 1e9, 2e9];
+    let got = trimmed_mean(&mut a, 0.2);
+    // Trim floor(0.2 * 11) = 2 from each end by hand:
+    // keeps [4, 5, 5, 5, 5, 6, 6] of the sorted values.
+    let expected = (4.0 + 5.0 + 5.0 + 5.0 + 5.0 + 6.0 + 6.0) / 7.0;
+    assert_eq!(got, expected);
+
+    // No trim: plain mean.
+    let mut a = [1.0, 2.0, 3.0];
+    assert_eq!(trimmed_mean(&mut a, 0.0), 2.0)
+}
+
+/// Returns the 1st- and 99th-percentile values of a batch
+/// of latency samples — the tails dashboards report —
+/// without fully sorting. Ranks are computed as
+/// `round(p/100 * (len - 1))`, the nearest-rank method on
+/// a zero-based index. Two quickselects share their
+/// partition work: the p1 select runs first, and because
+/// it leaves everything below its rank in place, the p99
+/// select only reworks the slice above that rank.
+/// Panics on an empty slice.
+#[cfg(feature = "std")]
+pub fn tail_percentiles(slice: &mut [u64]) -> (u64, u64) {
+    let nslice = slice.len();
+    assert!(nslice > 0, "percentiles of nothing");
+    let rank = |p: f64| (p / 100.0 * (nslice - 1) as f64).round() as usize;
+    let k1 = rank(1.0);
+    let k99 = rank(99.0);
+
+    let p1 = *select_nth(slice, k1);
+    // The p1 selection already settled [0, k1]; select the
+    // p99 rank within the remainder only.
+    let p99 = *select_nth(&mut slice[k1 ..], k99 - k1);
+    (p1, p99)
+}
+
+#[test]
+fn tail_percentiles_match_sorted() {
+    // Synthetic latency distribution: a bulk of fast
+    // responses and a long slow tail.
+    let mut rng = CheapRng::new();
+    let mut lat = Vec::with_capacity(10_000);
+    for _ in 0..10_000 {
+        let base = 100 + (rng.next_u64() % 400);
+        let spike = if rng.next_u64().is_multiple_of(100) {
+            rng.next_u64() % 50_000
+        } else {
+            0
+        };
+        lat.push(base + spike)
+    }
+    let mut sorted = lat.clone();
+    sorted.sort();
+    let n = lat.len();
+    let k1 = (0.01 * (n - 1) as f64).round() as usize;
+    let k99 = (0.99 * (n - 1) as f64).round() as usize;
+    assert_eq!(tail_percentiles(&mut lat), (sorted[k1], sorted[k99]))
+}
+
+// Bitonic compare-exchange network over a power-of-two
+// block. Every compare-exchange is a branchless min/max
+// pair on a fixed index pattern, which is exactly the
+// shape autovectorizers turn into SIMD lanes;
+// `std::simd` itself is still unstable, so the lanes are
+// left to the optimizer rather than spelled explicitly.
+#[cfg(feature = "simd")]
+fn bitonic_sort_block(block: &mut [i32]) {
+    let nblock = block.len();
+    debug_assert!(nblock.is_power_of_two());
+    let mut k = 2;
+    while k <= nblock {
+        let mut j = k / 2;
+        while j > 0 {
+            for i in 0..nblock {
+                let l = i ^ j;
+                if l > i {
+                    let (a, b) = (block[i], block[l]);
+                    let ascending = i & k == 0;
+                    block[i] = if ascending { a.min(b) } else { a.max(b) };
+                    block[l] = if ascending { a.max(b) } else { a.min(b) }
+                }
+            }
+            j /= 2
+        }
+        k *= 2
+    }
+}
+
+/// Sorts an `i32` slice like `quicksort()`, but finishes
+/// leaf blocks of exactly 8 or 16 elements with a
+/// branchless bitonic sorting network instead of
+/// insertion sort. The network's fixed compare-exchange
+/// pattern maps onto SIMD min/max lanes; odd-sized leaf
+/// remnants fall back to the scalar insertion sort, and
+/// builds without this `simd` feature simply don't have
+/// this entry point — plain `quicksort()` is the
+/// fallback.
+#[cfg(feature = "simd")]
+pub fn quicksort_i32_bitonic(slice: &mut [i32]) {
+    let mut stack: Vec<(usize, usize)> = Vec::new();
+    let mut lo = 0;
+    let mut hi = slice.len();
+    loop {
+        while hi - lo > INSERTION_THRESHOLD {
+            let (lt_end, gt_start) = partition_three_way(&mut slice[lo .. hi]);
+            let (lt_end, gt_start) = (lo + lt_end, lo + gt_start);
+            if lt_end - lo < hi - gt_start {
+                stack.push((lo, lt_end));
+                lo = gt_start
+            } else {
+                stack.push((gt_start, hi));
+                hi = lt_end
+            }
+        }
+        // Power-of-two leaves take the network; the rest
+        // go scalar.
+        match hi - lo {
+            8 | 16 => bitonic_sort_block(&mut slice[lo .. hi]),
+            _ => insertion_sort(&mut slice[lo .. hi]),
+        }
+        match stack.pop() {
+            Some((l, h)) => {
+                lo = l;
+                hi = h
+            }
+            None => return,
+        }
+    }
+}
+
+#[cfg(feature = "simd")]
+#[test]
+fn bitonic_blocks_match_scalar_sort() {
+    use rand::Rng;
+    for &n in &[8, 16] {
+        for _ in 0..50 {
+            let mut a: Vec<i32> = Vec::with_capacity(n);
+            for _ in 0..n {
+                a.push(rand::thread_rng().gen_range(-100, 100))
+            }
+            let mut expected = a.clone();
+            insertion_sort(&mut expected);
+            bitonic_sort_block(&mut a);
+            assert_eq!(a, expected)
+        }
+    }
+
+    // And the full sort agrees with the plain one.
+    let mut a: Vec<i32> = Vec::with_capacity(3000);
+    for _ in 0..3000 {
+        a.push(rand::thread_rng().gen_range(-1000, 1000))
+    }
+    let mut expected = a.clone();
+    quicksort(&mut expected);
+    quicksort_i32_bitonic(&mut a);
+    assert_eq!(a, expected)
+}
+
+/// Builds a deterministic length-`n` input engineered to
+/// make this crate's `quicksort()` partition poorly:
+/// McIlroy's killer adversary runs the real sort over
+/// lazily-valued elements and pins each value, at the
+/// moment it is first forced to commit, so the pivot the
+/// partition just chose looks nearly extremal. Because
+/// the values are derived by replaying the crate's own
+/// pivot logic, the output tracks that logic as it
+/// changes — regenerate rather than hard-coding samples.
+/// Intended as test and bench infrastructure: profiling
+/// worst cases, and validating any depth-bounded
+/// (introsort-style) fallback, which should sort this
+/// input in `O(n log n)` comparisons where plain
+/// `quicksort()` degrades toward `O(n^2)`.
+#[cfg(feature = "std")]
+pub fn adversarial_input(n: usize) -> Vec<u32> {
+    mcilroy_input(n, |elems| quicksort(elems))
+}
+
+// Shared McIlroy-adversary state: `vals[i]` is the
+// committed ("solid") value of element `i`, or `None`
+// while the element is still "gas" — notionally larger
+// than everything solid. `candidate` is the gas element
+// most recently seen being compared, i.e. the one the
+// sort is probably using as a pivot.
+#[cfg(feature = "std")]
+struct Adversary {
+    vals: std::cell::RefCell<Vec<Option<u32>>>,
+    nsolid: std::cell::Cell<u32>,
+    candidate: std::cell::Cell<usize>,
+}
+
+#[cfg(feature = "std")]
+impl Adversary {
+    fn freeze(&self, i: usize) {
+        let solid = self.nsolid.get();
+        self.vals.borrow_mut()[i] = Some(solid);
+        self.nsolid.set(solid + 1)
+    }
+}
+
+// A lazily-valued adversary element: just its original
+// index plus access to the adversary; all the cleverness
+// is in `cmp`.
+#[cfg(feature = "std")]
+struct Gas<'a>(usize, &'a Adversary);
+
+#[cfg(feature = "std")]
+impl<'a> PartialEq for Gas<'a> {
+    fn eq(&self, other: &Gas<'a>) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+#[cfg(feature = "std")]
+impl<'a> Eq for Gas<'a> {}
+
+#[cfg(feature = "std")]
+impl<'a> PartialOrd for Gas<'a> {
+    fn partial_cmp(&self, other: &Gas<'a>) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+#[cfg(feature = "std")]
+impl<'a> Ord for Gas<'a> {
+    fn cmp(&self, other: &Gas<'a>) -> Ordering {
+        let adv = self.1;
+        let (x, y) = (self.0, other.0);
+        let gas = |i: usize| adv.vals.borrow()[i].is_none();
+        // Two gas values: commit the likely pivot now, as
+        // small as possible, so the split it produces is
+        // lopsided.
+        if gas(x) && gas(y) {
+            if x == adv.candidate.get() {
+                adv.freeze(x)
+            } else {
+                adv.freeze(y)
+            }
+        }
+        // Whichever side is still gas is being compared
+        // against a solid value — remember it as the
+        // presumptive pivot.
+        if gas(x) {
+            adv.candidate.set(x)
+        } else if gas(y) {
+            adv.candidate.set(y)
+        }
+        let vals = adv.vals.borrow();
+        let solid_or_gas = |v: Option<u32>| v.unwrap_or(u32::MAX);
+        solid_or_gas(vals[x]).cmp(&solid_or_gas(vals[y]))
+    }
+}
+
+// Runs `sort` over `n` gas elements, letting the adversary
+// pin values as the sort's own comparisons force them, and
+// returns the resulting concrete input — which replays the
+// same comparison pattern when fed back to that sort.
+#[cfg(feature = "std")]
+fn mcilroy_input(
+    n: usize,
+    sort: impl for<'a> FnOnce(&mut [Gas<'a>]),
+) -> Vec<u32> {
+    use std::cell::{Cell, RefCell};
+
+    let adv = Adversary {
+        vals: RefCell::new(vec![None; n]),
+        nsolid: Cell::new(0),
+        candidate: Cell::new(0),
+    };
+    let mut elems: Vec<Gas> = (0..n).map(|i| Gas(i, &adv)).collect();
+    sort(&mut elems);
+    drop(elems);
+
+    // Any elements never forced to commit stay above the
+    // solid range, distinct and in index order.
+    let vals = adv.vals.into_inner();
+    let mut next = adv.nsolid.get();
+    vals.into_iter()
+        .map(|v| {
+            v.unwrap_or_else(|| {
+                let solid = next;
+                next += 1;
+                solid
+            })
+        })
+        .collect()
+}
+
+#[test]
+fn adversarial_input_inflates_comparisons() {
+    use std::cell::Cell;
+
+    let n = 2000;
+    let count_sort = |vals: &[u32]| {
+        let count = Cell::new(0);
+        let mut a: Vec<CountedCmp> = vals
+            .iter()
+            .map(|&v| CountedCmp(v as i64, &count))
+            .collect();
+        quicksort(&mut a);
+        count.get()
+    };
+
+    let bad = adversarial_input(n);
+    assert_eq!(count_sort(&bad), count_sort(&bad), "not deterministic");
+
+    // A random permutation of the same values is the
+    // well-behaved baseline the adversary must beat by a
+    // wide margin.
+    let mut rng = CheapRng::new();
+    let mut benign = bad.clone();
+    for i in (1..benign.len()).rev() {
+        let j = (rng.next_u64() % (i as u64 + 1)) as usize;
+        benign.swap(i, j)
+    }
+    let (bad_cmps, benign_cmps) = (count_sort(&bad), count_sort(&benign));
+    assert!(
+        bad_cmps > 4 * benign_cmps,
+        "adversarial {} vs benign {}",
+        bad_cmps,
+        benign_cmps
+    )
+}
+
+/// Sorts the slice ascending in the float total order,
+/// like `quicksort_floats()`, and returns the Gini
+/// coefficient of the values — 0.0 when everyone holds an
+/// equal share, approaching 1.0 as one value holds
+/// everything. Computed from the sorted sequence with the
+/// standard `O(n)` rank formula. The coefficient is only
+/// meaningful for non-negative data, so any negative or
+/// non-finite value panics; an empty or all-zero slice
+/// has no inequality to measure and returns 0.0.
+///
+/// # Examples
+///
+/// ```
+/// let mut a = [5.0, 5.0, 5.0, 5.0];
+/// assert_eq!(quicksort::quicksort_gini(&mut a), 0.0);
+/// ```
+#[cfg(feature = "std")]
+pub fn quicksort_gini(slice: &mut [f64]) -> f64 {
+    for v in slice.iter() {
+        if !(*v >= 0.0 && v.is_finite()) {
+            panic!("gini of negative or non-finite value")
+        }
+    }
+    quicksort_floats(slice);
+
+    let nslice = slice.len();
+    let total: f64 = slice.iter().sum();
+    if total == 0.0 {
+        return 0.0
+    }
+    // G = 2 Σ i·xᵢ / (n Σ xᵢ) - (n + 1) / n with
+    // one-based ranks over the ascending order.
+    let weighted: f64 = slice
+        .iter()
+        .enumerate()
+        .map(|(i, v)| (i + 1) as f64 * v)
+        .sum();
+    let nslice = nslice as f64;
+    2.0 * weighted / (nslice * total) - (nslice + 1.0) / nslice
+}
+
+#[test]
+fn quicksort_gini_extremes() {
+    // Perfect equality.
+    let mut a = [3.0; 40];
+    assert_eq!(quicksort_gini(&mut a), 0.0);
+
+    // One value holds everything: G = (n - 1) / n.
+    let n = 1000;
+    let mut a = vec![0.0; n];
+    a[n / 2] = 17.0;
+    let g = quicksort_gini(&mut a);
+    assert!((g - (n as f64 - 1.0) / n as f64).abs() < 1e-9);
+    assert!(is_sorted_by(&a, |x, y| x.total_cmp(y)));
+
+    // A middling case sits strictly between.
+    let mut a = [1.0, 2.0, 3.0, 4.0];
+    let g = quicksort_gini(&mut a);
+    assert!(g > 0.2 && g < 0.3)
+}
+
+/// Sorts struct-of-arrays data by `key` ascending: `key`
+/// is the key column and each entry of `payloads` is a
+/// flat byte column whose per-element stride is the
+/// matching entry of `elem_sizes`, so every swap the sort
+/// makes in `key` is mirrored as a stride-sized byte-block
+/// swap in every payload column. Rows stay aligned across
+/// columns without ever converting to array-of-structs
+/// form. Panics if the column counts disagree or any
+/// payload's length is not `key.len()` times its stride.
+/// Equal keys keep no particular relative order.
+#[cfg(feature = "std")]
+pub fn quicksort_soa(
+    key: &mut [u32],
+    payloads: &mut [&mut [u8]],
+    elem_sizes: &[usize],
+) {
+    let nrows = key.len();
+    assert_eq!(
+        payloads.len(),
+        elem_sizes.len(),
+        "quicksort_soa: payload and stride counts differ",
+    );
+    for (payload, &stride) in payloads.iter().zip(elem_sizes) {
+        assert_eq!(
+            payload.len(),
+            nrows * stride,
+            "quicksort_soa: payload length does not match key count",
+        )
+    }
+
+    // Order an index permutation by key, invert it to
+    // per-row destinations, and cycle-follow — mirroring
+    // each row swap across every column.
+    let mut perm: Vec<usize> = (0..nrows).collect();
+    quicksort_by(&mut perm, |&i, &j| key[i].cmp(&key[j]));
+    let mut dest = vec![0; nrows];
+    for (target, &source) in perm.iter().enumerate() {
+        dest[source] = target
+    }
+    for i in 0..nrows {
+        while dest[i] != i {
+            let j = dest[i];
+            key.swap(i, j);
+            for (payload, &stride) in
+                payloads.iter_mut().zip(elem_sizes)
+            {
+                for b in 0..stride {
+                    payload.swap(i * stride + b, j * stride + b)
+                }
+            }
+            dest.swap(i, j)
+        }
+    }
+}
+
+#[test]
+fn quicksort_soa_keeps_columns_aligned() {
+    // Key column plus a 1-byte tag column and a 4-byte
+    // little-endian copy of the key.
+    let mut key: Vec<u32> = vec![30, 10, 40, 20, 50];
+    let mut tags: Vec<u8> = key.iter().map(|k| (k / 10) as u8).collect();
+    let mut wide: Vec<u8> =
+        key.iter().flat_map(|k| k.to_le_bytes()).collect();
+    {
+        let mut payloads = [&mut tags[..], &mut wide[..]];
+        quicksort_soa(&mut key, &mut payloads, &[1, 4]);
+    }
+    assert_eq!(key, [10, 20, 30, 40, 50]);
+    for (i, &k) in key.iter().enumerate() {
+        assert_eq!(tags[i], (k / 10) as u8);
+        let mut le = [0; 4];
+        le.copy_from_slice(&wide[i * 4 .. (i + 1) * 4]);
+        assert_eq!(u32::from_le_bytes(le), k)
+    }
+}
+
+/// Sorts a slice by a lexicographic chain of key
+/// extractors: `quicksort_by_keys!(v, |r| r.a, |r|
+/// Reverse(r.b), |r| r.c)` sorts by `a` ascending, then
+/// `b` descending, then `c` ascending. Each argument
+/// after the slice is a closure from `&T` to any `Ord`
+/// key, with per-key direction via `std::cmp::Reverse`;
+/// later keys only break ties the earlier ones leave.
+/// Expands to a single `quicksort_by()` call.
+///
+/// # Examples
+///
+/// ```
+/// #[macro_use] extern crate quicksort;
+/// # fn main() {
+/// let mut v = [(2, 1), (1, 1), (2, 3)];
+/// quicksort_by_keys!(&mut v, |r: &(i32, i32)| r.0, |r: &(i32, i32)| std::cmp::Reverse(r.1));
+/// assert_eq!(v, [(1, 1), (2, 3), (2, 1)]);
+/// # }
+/// ```
+#[macro_export]
+macro_rules! quicksort_by_keys {
+    ($slice:expr $(, $key:expr)+ $(,)?) => {
+        $crate::quicksort_by($slice, |a, b| {
+            let ord = ::std::cmp::Ordering::Equal;
+            $(
+                let ord = ord.then_with(|| {
+                    ::std::cmp::Ord::cmp(&($key)(a), &($key)(b))
+                });
+            )+
+            ord
+        })
+    };
+}
+
+#[test]
+fn quicksort_by_keys_composite_order() {
+    use std::cmp::Reverse;
+
+    // Ascending dept, descending score, ascending first
+    // letter of name.
+    let mut rows = vec![
+        ("ada", 1, 90),
+        ("bob", 2, 70),
+        ("cat", 1, 95),
+        ("dan", 1, 90),
+        ("eve", 2, 70),
+    ];
+    quicksort_by_keys!(
+        &mut rows,
+        |r: &(&str, i32, i32)| r.1,
+        |r: &(&str, i32, i32)| Reverse(r.2),
+        |r: &(&str, i32, i32)| r.0.as_bytes()[0],
+    );
+    assert_eq!(
+        rows,
+        [
+            ("cat", 1, 95),
+            ("ada", 1, 90),
+            ("dan", 1, 90),
+            ("bob", 2, 70),
+            ("eve", 2, 70),
+        ]
+    );
+
+    // A single key degenerates to a plain keyed sort.
+    let mut a = [3, 1, 2];
+    quicksort_by_keys!(&mut a, |x: &i32| *x);
+    assert_eq!(a, [1, 2, 3])
+}
+
+/// Sorts the slice ascending in the float total order and
+/// returns counts per equal-width histogram bin, `bins`
+/// bins spanning `[min, max]`. A value on an interior bin
+/// boundary counts in the bin above it, and `max` itself
+/// counts in the last bin; values outside `[min, max]`
+/// (NaN included) are dropped from the counts, not
+/// clamped. Sorting first means the binning is one linear
+/// pass over each bin's contiguous run. Panics if `bins`
+/// is zero or `min` is not below `max`.
+///
+/// # Examples
+///
+/// ```
+/// let mut a = [0.5, 2.5, 1.5, 2.6, 9.0];
+/// let counts = quicksort::quicksort_histogram(&mut a, 3, 0.0, 3.0);
+/// assert_eq!(counts, [1, 1, 2]);
+/// ```
+#[cfg(feature = "std")]
+pub fn quicksort_histogram(
+    slice: &mut [f64],
+    bins: usize,
+    min: f64,
+    max: f64,
+) -> Vec<usize> {
+    if bins == 0 || min.partial_cmp(&max) != Some(Ordering::Less) {
+        panic!("histogram with empty range")
+    }
+    quicksort_floats(slice);
+
+    let width = (max - min) / bins as f64;
+    let mut counts = vec![0; bins];
+    // Sorted order visits each bin as one contiguous run,
+    // with the out-of-range prefix and suffix skipped.
+    let mut bin = 0;
+    for &v in slice.iter() {
+        if v < min {
+            continue
+        }
+        if v > max {
+            break
+        }
+        // Advance past bins this value has outgrown; the
+        // top boundary folds into the last bin.
+        while bin + 1 < bins && v >= min + (bin + 1) as f64 * width {
+            bin += 1
+        }
+        counts[bin] += 1
+    }
+    counts
+}
+
+#[test]
+fn quicksort_histogram_boundaries() {
+    // Bins of width 1 over [0, 4): boundary values go up.
+    let mut a = [3.9, 0.0, 1.0, 0.5, 2.0, 2.999, 4.0, -0.1, 4.1, f64::NAN];
+    let counts = quicksort_histogram(&mut a, 4, 0.0, 4.0);
+    // 0.0 and 0.5 in bin 0; 1.0 in bin 1; 2.0 and 2.999 in
+    // bin 2; 3.9 and the max boundary 4.0 in bin 3. The
+    // -0.1, 4.1, and NaN are dropped.
+    assert_eq!(counts, [2, 1, 2, 2]);
+    assert_eq!(counts.iter().sum::<usize>(), 7);
+    assert!(is_sorted_by(&a, |x, y| x.total_cmp(y)))
+}
+
+/// Reorders the slice to match an externally decided
+/// order: the element originally at `perm[i]` ends up at
+/// position `i`. Cycle-following does this in place with
+/// one move per displaced element and `O(n)` auxiliary
+/// index space. The natural companion to this crate's
+/// permutation-returning functions — `quicksort_swap_plan()`
+/// and friends decide the order, this applies it. Panics
+/// unless `perm` is a permutation of `0..slice.len()`.
+///
+/// # Examples
+///
+/// ```
+/// let mut a = ["c", "a", "b"];
+/// quicksort::apply_permutation(&mut a, &[1, 2, 0]);
+/// assert_eq!(a, ["a", "b", "c"]);
+/// ```
+#[cfg(feature = "std")]
+pub fn apply_permutation<T>(slice: &mut [T], perm: &[usize]) {
+    let nslice = slice.len();
+    assert_eq!(perm.len(), nslice, "permutation has wrong length");
+    let mut seen = vec![false; nslice];
+    for &source in perm {
+        if source >= nslice || seen[source] {
+            panic!("not a permutation")
+        }
+        seen[source] = true
+    }
+
+    // Invert to per-element destinations, then
+    // cycle-follow as in the keyed sorts.
+    let mut dest = vec![0; nslice];
+    for (target, &source) in perm.iter().enumerate() {
+        dest[source] = target
+    }
+    for i in 0..nslice {
+        while dest[i] != i {
+            let j = dest[i];
+            slice.swap(i, j);
+            dest.swap(i, j)
+        }
+    }
+}
+
+#[test]
+fn apply_permutation_reorders() {
+    let mut a = [10, 20, 30, 40, 50];
+    apply_permutation(&mut a, &[3, 0, 4, 1, 2]);
+    assert_eq!(a, [40, 10, 50, 20, 30]);
+
+    // Round trip: applying an argsort permutation sorts.
+    let orig = [5, 3, 9, 1, 7, 1];
+    let mut perm: Vec<usize> = (0..orig.len()).collect();
+    quicksort_by(&mut perm, |&i, &j| orig[i].cmp(&orig[j]));
+    let mut a = orig;
+    apply_permutation(&mut a, &perm);
+    assert!(is_sorted(&a))
+}
+
+#[test]
+#[should_panic(expected = "not a permutation")]
+fn apply_permutation_rejects_duplicates() {
+    let mut a = [1, 2, 3];
+    apply_permutation(&mut a, &[0, 0, 2])
+}
+
+/// Sorts the slice and reports the shape of the
+/// comparison tree as `(min, max, mean)` recursion depth
+/// over all comparisons made: a well-balanced run keeps
+/// every comparison within a few levels of `log2(n)`,
+/// while a lopsided one stretches the maximum and drags
+/// the mean up with it. The top-level partition is depth
+/// 0. A slice too short to compare reports `(0, 0, 0.0)`.
+/// Analysis-oriented: the sort itself is the plain
+/// recursive partition without the small-slice cutover,
+/// so every comparison has a well-defined depth.
+#[cfg(feature = "std")]
+pub fn quicksort_comparison_depths<T: Ord>(
+    slice: &mut [T],
+) -> (usize, usize, f64) {
+    // min, max, sum, count over comparison depths.
+    struct Depths(usize, usize, u64, u64);
+
+    fn depth_sort<T: Ord>(
+        slice: &mut [T],
+        depth: usize,
+        acc: &mut Depths,
+    ) {
+        if slice.len() < 2 {
+            return
+        }
+        // The unchecked partition, so test builds don't
+        // pollute the statistics with invariant-check
+        // comparisons.
+        let mut compare = |a: &T, b: &T| {
+            acc.0 = acc.0.min(depth);
+            acc.1 = acc.1.max(depth);
+            acc.2 += depth as u64;
+            acc.3 += 1;
+            a.cmp(b)
+        };
+        let pivot = partition_by_checked(slice, &mut compare, false);
+        depth_sort(&mut slice[.. pivot], depth + 1, acc);
+        depth_sort(&mut slice[pivot + 1 ..], depth + 1, acc)
+    }
+
+    let mut acc = Depths(usize::MAX, 0, 0, 0);
+    depth_sort(slice, 0, &mut acc);
+    debug_assert!(is_sorted(slice));
+    if acc.3 == 0 {
+        return (0, 0, 0.0)
+    }
+    (acc.0, acc.1, acc.2 as f64 / acc.3 as f64)
+}
+
+#[test]
+fn quicksort_comparison_depths_balance() {
+    // A shuffled input partitions reasonably evenly.
+    let mut rng = CheapRng::new();
+    let n = 1024;
+    let mut balanced: Vec<u64> = (0..n).collect();
+    for i in (1..balanced.len()).rev() {
+        let j = (rng.next_u64() % (i as u64 + 1)) as usize;
+        balanced.swap(i, j)
+    }
+    let (bal_min, bal_max, bal_mean) =
+        quicksort_comparison_depths(&mut balanced);
+    assert!(is_sorted(&balanced));
+    assert_eq!(bal_min, 0);
+    // log2(1024) = 10; random splits stay within a small
+    // constant of that.
+    assert!(bal_max < 40, "balanced max depth {}", bal_max);
+
+    // An input built adversarially against this very
+    // sort runs far deeper.
+    let killer = mcilroy_input(n as usize, |elems| {
+        quicksort_comparison_depths(elems);
+    });
+    let mut adversarial: Vec<u64> =
+        killer.iter().map(|&v| v as u64).collect();
+    let (_, adv_max, adv_mean) =
+        quicksort_comparison_depths(&mut adversarial);
+    assert!(is_sorted(&adversarial));
+    assert!(adv_max > 2 * bal_max, "adversarial max depth {}", adv_max);
+    assert!(
+        adv_mean > 2.0 * bal_mean,
+        "means {} vs {}",
+        adv_mean,
+        bal_mean,
+    )
+}
+
+/// Sorts a slice of indices into `data` so that the
+/// values they reference come out ascending —
+/// `data[indices[0]] <= data[indices[1]] <= ...` — without
+/// touching `data` itself. The indices can be any subset,
+/// in any order, with repeats; this is the explicit
+/// "argsort these particular indices" operation where the
+/// keyed sorts in this crate always permute whole slices.
+/// Panics if any index is out of range for `data`.
+///
+/// # Examples
+///
+/// ```
+/// let data = [50, 10, 40, 20, 30];
+/// let mut picks = [4, 0, 1];
+/// quicksort::sort_indices_by(&mut picks, &data);
+/// assert_eq!(picks, [1, 4, 0]);
+/// ```
+#[cfg(feature = "std")]
+pub fn sort_indices_by<T: Ord>(indices: &mut [usize], data: &[T]) {
+    for &i in indices.iter() {
+        assert!(i < data.len(), "index out of range for data")
+    }
+    quicksort_by(indices, |&i, &j| data[i].cmp(&data[j]))
+}
+
+#[test]
+fn sort_indices_by_orders_referenced_values() {
+    let data = [9, 2, 7, 2, 5, 1, 8];
+    let mut picks = vec![6, 3, 5, 0, 2];
+    sort_indices_by(&mut picks, &data);
+    assert!(picks.windows(2).all(|w| data[w[0]] <= data[w[1]]));
+    // Same subset, just reordered.
+    let mut sorted_picks = picks.clone();
+    quicksort(&mut sorted_picks);
+    assert_eq!(sorted_picks, [0, 2, 3, 5, 6]);
+    // Untouched data.
+    assert_eq!(data, [9, 2, 7, 2, 5, 1, 8])
+}
+
+/// Sorts ascending or descending with the direction
+/// chosen at compile time: `quicksort_const::<_, false>`
+/// sorts ascending, `quicksort_const::<_, true>`
+/// descending. The `DESC` const generic is folded away
+/// during monomorphization, so the descending
+/// instantiation carries no per-comparison direction
+/// branch the way a runtime flag would — each direction
+/// compiles to its own straight-line comparator.
+///
+/// # Examples
+///
+/// ```
+/// let mut a = [2, 3, 1];
+/// quicksort::quicksort_const::<_, true>(&mut a);
+/// assert_eq!(a, [3, 2, 1]);
+/// ```
+#[cfg(feature = "std")]
+pub fn quicksort_const<T: Ord, const DESC: bool>(slice: &mut [T]) {
+    quicksort_by(slice, |a, b| {
+        if DESC {
+            b.cmp(a)
+        } else {
+            a.cmp(b)
+        }
+    })
+}
+
+#[test]
+fn quicksort_const_both_directions() {
+    let mut rng = CheapRng::new();
+    let mut a: Vec<u64> = (0..500).map(|_| rng.next_u64() % 100).collect();
+    let mut b = a.clone();
+    quicksort_const::<_, false>(&mut a);
+    assert!(is_sorted(&a));
+    quicksort_const::<_, true>(&mut b);
+    assert!(b.windows(2).all(|w| w[0] >= w[1]));
+    b.reverse();
+    assert_eq!(a, b)
+}
+
+/// An incremental quicksort that streams settled
+/// positions: each `next()` call does one partitioning
+/// step and yields an index whose element has provably
+/// reached its final sorted position — the pivot each
+/// partition places, or a range narrowed to a single
+/// element. Progressive consumers (a UI repainting rows,
+/// say) can act on each position immediately while the
+/// rest of the slice is still unsorted. Every index in
+/// `0..len` is yielded exactly once, in data-dependent
+/// order, and once the iterator is exhausted the borrowed
+/// slice is fully sorted.
+///
+/// # Examples
+///
+/// ```
+/// let mut a = [3, 1, 2];
+/// let settled: Vec<usize> =
+///     quicksort::SortStream::new(&mut a).collect();
+/// assert_eq!(settled.len(), 3);
+/// assert_eq!(a, [1, 2, 3]);
+/// ```
+#[cfg(feature = "std")]
+pub struct SortStream<'a, T> {
+    slice: &'a mut [T],
+    // Pending unsorted subranges, as in the iterative
+    // sorts.
+    stack: Vec<(usize, usize)>,
+}
+
+#[cfg(feature = "std")]
+impl<'a, T: Ord> SortStream<'a, T> {
+    pub fn new(slice: &'a mut [T]) -> Self {
+        let nslice = slice.len();
+        SortStream { slice, stack: vec![(0, nslice)] }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<'a, T: Ord> Iterator for SortStream<'a, T> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        loop {
+            let (lo, hi) = self.stack.pop()?;
+            match hi - lo {
+                0 => continue,
+                // A lone element is settled by exclusion.
+                1 => return Some(lo),
+                _ => {
+                    let pivot = lo
+                        + partition(&mut self.slice[lo .. hi]).unwrap();
+                    self.stack.push((pivot + 1, hi));
+                    self.stack.push((lo, pivot));
+                    return Some(pivot)
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn sort_stream_settles_every_position_once() {
+    let mut rng = CheapRng::new();
+    let mut a: Vec<u64> = (0..300).map(|_| rng.next_u64() % 50).collect();
+    let settled: Vec<usize> = SortStream::new(&mut a).collect();
+    assert!(is_sorted(&a));
+
+    // Exactly once each.
+    let mut seen = settled.clone();
+    quicksort(&mut seen);
+    let expected: Vec<usize> = (0..a.len()).collect();
+    assert_eq!(seen, expected);
+
+    // A settled position really is final: replay and check
+    // against the sorted result as each index arrives.
+    let mut b: Vec<u64> = (0..300).map(|_| rng.next_u64() % 50).collect();
+    let sorted = {
+        let mut s = b.clone();
+        quicksort(&mut s);
+        s
+    };
+    let mut stream = SortStream::new(&mut b);
+    while let Some(i) = stream.next() {
+        assert_eq!(stream.slice[i], sorted[i])
+    }
+}
+
+/// Sorts `(offset, length)` descriptors of
+/// variable-length records packed into a flat byte
+/// `arena`, ordering them by `compare` applied to the
+/// byte ranges they reference. Only the two-word
+/// descriptors move; the records themselves stay put, so
+/// arbitrarily large records cost nothing to reorder.
+/// Panics if any descriptor reaches past the end of the
+/// arena.
+///
+/// # Examples
+///
+/// ```
+/// let arena = b"pearfigplum";
+/// let mut recs = [(0, 4), (4, 3), (7, 4)];
+/// quicksort::quicksort_arena(&mut recs, arena, |a, b| a.cmp(b));
+/// assert_eq!(recs, [(4, 3), (0, 4), (7, 4)]);
+/// ```
+#[cfg(feature = "std")]
+pub fn quicksort_arena(
+    offsets: &mut [(usize, usize)],
+    arena: &[u8],
+    mut compare: impl FnMut(&[u8], &[u8]) -> Ordering,
+) {
+    for &(offset, len) in offsets.iter() {
+        assert!(
+            offset.checked_add(len).is_some_and(|end| end <= arena.len()),
+            "descriptor outside arena",
+        )
+    }
+    quicksort_by(offsets, |&(ao, al), &(bo, bl)| {
+        compare(&arena[ao .. ao + al], &arena[bo .. bo + bl])
+    })
+}
+
+#[test]
+fn quicksort_arena_orders_descriptors_by_content() {
+    // Records packed back to back, descriptors shuffled.
+    let words = ["delta", "ax", "charlie", "bee", "ax"];
+    let mut arena = Vec::new();
+    let mut recs = Vec::new();
+    for w in &words {
+        recs.push((arena.len(), w.len()));
+        arena.extend_from_slice(w.as_bytes())
+    }
+    recs.swap(0, 3);
+    recs.swap(1, 4);
+
+    quicksort_arena(&mut recs, &arena, |a, b| a.cmp(b));
+    let sorted: Vec<&str> = recs
+        .iter()
+        .map(|&(o, l)| std::str::from_utf8(&arena[o .. o + l]).unwrap())
+        .collect();
+    assert_eq!(sorted, ["ax", "ax", "bee", "charlie", "delta"]);
+    // The arena itself never moves.
+    assert_eq!(arena, b"deltaaxcharliebeeax")
+}
+
+#[test]
+#[should_panic(expected = "outside arena")]
+fn quicksort_arena_rejects_bad_range() {
+    let arena = [0u8; 8];
+    let mut recs = [(6, 3), (0, 2)];
+    quicksort_arena(&mut recs, &arena, |a, b| a.cmp(b))
+}